@@ -0,0 +1,50 @@
+/* C interface to the myforth interpreter, built as libmyforth.so by `cargo build`.
+ *
+ * every function that can fail returns 0 on success and -1 on failure,
+ * `myforth_last_error` then says what went wrong. */
+
+#ifndef MYFORTH_H
+#define MYFORTH_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+typedef struct MyForth MyForth;
+
+/* a new interpreter session, words and variables stick around between calls to `myforth_eval` */
+MyForth *myforth_new(void);
+void myforth_free(MyForth *forth);
+
+/* compile and run `code`, a nul terminated UTF-8 string. output goes to stdout.
+ * when it fails the session is rolled back to how it was before the call */
+int myforth_eval(MyForth *forth, const char *code);
+
+int myforth_push_int(MyForth *forth, int64_t n);
+int myforth_push_float(MyForth *forth, double f);
+/* the string is copied */
+int myforth_push_str(MyForth *forth, const char *s);
+
+/* a value of the wrong type is left on the stack */
+int myforth_pop_int(MyForth *forth, int64_t *out);
+/* integers are converted */
+int myforth_pop_float(MyForth *forth, double *out);
+/* NULL on failure, otherwise a new string that has to be freed with `myforth_free_str` */
+char *myforth_pop_str(MyForth *forth);
+void myforth_free_str(char *s);
+
+/* number of values on the stack */
+size_t myforth_depth(MyForth *forth);
+
+/* NULL if the last call went fine, otherwise the error message.
+ * it stays valid until the next call on `forth` */
+const char *myforth_last_error(MyForth *forth);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
@@ -0,0 +1,84 @@
+/* exercises the C interface, run it with `make` in this directory */
+
+#include <stdio.h>
+#include <string.h>
+#include "myforth.h"
+
+static int failures = 0;
+
+#define CHECK(cond) do { \
+    if (!(cond)) { \
+        fprintf(stderr, "%s:%d: check failed: %s\n", __FILE__, __LINE__, #cond); \
+        failures++; \
+    } \
+} while (0)
+
+/* only exported by debug builds of the library, panics inside the interpreter */
+int myforth_debug_panic(MyForth *forth);
+
+int main(void) {
+    MyForth *forth = myforth_new();
+    int64_t n = 0;
+    double f = 0;
+
+    /* words defined in one call can be used in the next */
+    CHECK(myforth_eval(forth, "defword sq ( n -- n ) dup * return") == 0);
+    CHECK(myforth_last_error(forth) == NULL);
+    CHECK(myforth_push_int(forth, 7) == 0);
+    CHECK(myforth_eval(forth, "sq") == 0);
+    CHECK(myforth_pop_int(forth, &n) == 0 && n == 49);
+
+    CHECK(myforth_push_float(forth, 1.5) == 0);
+    CHECK(myforth_eval(forth, "2 *") == 0);
+    CHECK(myforth_pop_float(forth, &f) == 0 && f == 3.0);
+
+    CHECK(myforth_push_str(forth, "hello") == 0);
+    char *s = myforth_pop_str(forth);
+    CHECK(s != NULL && strcmp(s, "hello") == 0);
+    myforth_free_str(s);
+
+    /* errors leave the stack alone and explain themselves */
+    CHECK(myforth_eval(forth, "1 2 nope") == -1);
+    CHECK(myforth_last_error(forth) != NULL && strstr(myforth_last_error(forth), "nope") != NULL);
+    CHECK(myforth_depth(forth) == 0);
+
+    CHECK(myforth_eval(forth, "\"x\"") == 0);
+    CHECK(myforth_pop_int(forth, &n) == -1);
+    CHECK(myforth_depth(forth) == 1);
+    CHECK(myforth_pop_float(forth, &f) == -1);
+    s = myforth_pop_str(forth);
+    CHECK(s != NULL && strcmp(s, "x") == 0);
+    myforth_free_str(s);
+    CHECK(myforth_pop_str(forth) == NULL);
+    CHECK(strstr(myforth_last_error(forth), "empty") != NULL);
+
+    /* a panic comes back as an error and the session can still be used */
+    CHECK(myforth_debug_panic(forth) == -1);
+    CHECK(myforth_last_error(forth) != NULL && strstr(myforth_last_error(forth), "panicked") != NULL);
+    CHECK(myforth_eval(forth, "2 sq") == 0);
+    CHECK(myforth_pop_int(forth, &n) == 0 && n == 4);
+
+    /* so does arithmetic that can't be done */
+    CHECK(myforth_eval(forth, "1 0 /") == -1);
+    CHECK(strstr(myforth_last_error(forth), "division by zero") != NULL);
+    CHECK(myforth_depth(forth) == 0);
+
+    /* any call that goes fine clears the error of the one before */
+    CHECK(myforth_eval(forth, "nope") == -1);
+    CHECK(myforth_push_int(forth, 1) == 0);
+    CHECK(myforth_last_error(forth) == NULL);
+    CHECK(myforth_eval(forth, "nope") == -1);
+    CHECK(myforth_push_float(forth, 1.5) == 0);
+    CHECK(myforth_last_error(forth) == NULL);
+    CHECK(myforth_eval(forth, "nope") == -1);
+    CHECK(myforth_depth(forth) == 2);
+    CHECK(myforth_last_error(forth) == NULL);
+
+    myforth_free(forth);
+    if (failures > 0) {
+        fprintf(stderr, "%d check(s) failed\n", failures);
+        return 1;
+    }
+    printf("all checks passed\n");
+    return 0;
+}
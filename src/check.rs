@@ -0,0 +1,606 @@
+// static stack-effect checker
+//
+// walks every path through the compiled program keeping track of how deep the stack is,
+// without running anything. this catches underflows, `if` branches that leave a different
+// number of values on the stack and `while` loops that grow or shrink the stack every iteration.
+// words are checked on their own and summarized as an `Effect` so calls don't need to be followed.
+// words declared with a `( a b -- c )` signature are also checked against it, including the
+// types of the values when the signature names them (`n`, `s`, `bool`, `list`)
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use crate::error::*;
+use crate::{Op, Program, Word};
+
+// how many values a word takes off the stack and how many it leaves behind, `( inputs -- outputs )`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Effect {
+    pub inputs: usize,
+    pub outputs: usize,
+}
+
+// what a value on the stack is known to be without running the program
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Ty {
+    Any,
+    Number,
+    Str,
+    Boolean,
+    List,
+}
+
+impl Ty {
+    // names in a signature that pin down a type, a number can be tacked on to tell them apart: `n1 n2 -- n`
+    pub fn from_name(name: &str) -> Ty {
+        match name.trim_end_matches(|c: char| c.is_ascii_digit()) {
+            "n" => Ty::Number,
+            "s" | "str" => Ty::Str,
+            "bool" | "flag" => Ty::Boolean,
+            "list" => Ty::List,
+            _ => Ty::Any,
+        }
+    }
+
+    fn join(self, other: Ty) -> Ty {
+        if self == other { self } else { Ty::Any }
+    }
+
+    fn fits(self, expected: Ty) -> bool {
+        self == Ty::Any || expected == Ty::Any || self == expected
+    }
+}
+
+impl fmt::Display for Ty {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Ty::Any => write!(f, "anything"),
+            Ty::Number => write!(f, "Number"),
+            Ty::Str => write!(f, "Str"),
+            Ty::Boolean => write!(f, "Boolean"),
+            Ty::List => write!(f, "List"),
+        }
+    }
+}
+
+// a declared stack effect, `defword mod ( a b -- r )`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Signature {
+    pub inputs: Vec<String>,
+    pub outputs: Vec<String>,
+}
+
+impl Signature {
+    pub fn effect(&self) -> Effect {
+        Effect { inputs: self.inputs.len(), outputs: self.outputs.len() }
+    }
+
+    pub fn input_types(&self) -> Vec<Ty> {
+        self.inputs.iter().map(|name| Ty::from_name(name)).collect()
+    }
+
+    pub fn output_types(&self) -> Vec<Ty> {
+        self.outputs.iter().map(|name| Ty::from_name(name)).collect()
+    }
+
+    fn is_typed(&self) -> bool {
+        self.input_types().iter().chain(self.output_types().iter()).any(|t| *t != Ty::Any)
+    }
+}
+
+impl fmt::Display for Signature {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "(")?;
+        for name in &self.inputs {
+            write!(f, " {}", name)?;
+        }
+        write!(f, " --")?;
+        for name in &self.outputs {
+            write!(f, " {}", name)?;
+        }
+        write!(f, " )")
+    }
+}
+
+// types an instruction expects to find on the stack, top of the stack first
+fn operand_types(op: &Op) -> Vec<Ty> {
+    match op {
+        Op::Add | Op::Sub | Op::Mul | Op::Div => vec![Ty::Number, Ty::Number],
+        Op::Eq | Op::Gt | Op::Lt | Op::Gteq | Op::Lteq => vec![Ty::Number, Ty::Number],
+        Op::Floor => vec![Ty::Number],
+        Op::Index => vec![Ty::Number, Ty::List],
+        Op::ListLen | Op::ListPop | Op::Reverse | Op::First | Op::Last => vec![Ty::List],
+        Op::ListPush | Op::Contains | Op::IndexOf => vec![Ty::Any, Ty::List],
+        Op::ListSet | Op::ListInsert => vec![Ty::Any, Ty::Number, Ty::List],
+        Op::ListRemove => vec![Ty::Number, Ty::List],
+        Op::Concat => vec![Ty::List, Ty::List],
+        Op::Slice => vec![Ty::Number, Ty::Number, Ty::List],
+        Op::ToList => vec![Ty::Number],
+        Op::Getenv => vec![Ty::Str],
+        Op::Exit => vec![Ty::Number],
+        _ => vec![],
+    }
+}
+
+// (pops, pushes) of instructions that always move the stack the same way
+fn simple_effect(op: &Op) -> (usize, usize) {
+    match op {
+        Op::PushInteger(_) | Op::PushFloat(_) | Op::PushStr(_) | Op::PushBool(_) | Op::PushList(_) | Op::PushNull => (0, 1),
+        Op::Add | Op::Sub | Op::Mul | Op::Div => (2, 1),
+        Op::Eq | Op::Gt | Op::Lt | Op::Gteq | Op::Lteq => (2, 1),
+        Op::Floor => (1, 1),
+        Op::Dup => (1, 2),
+        Op::Swap => (2, 2),
+        Op::Drop => (1, 0),
+        Op::Over => (2, 3),
+        Op::Rotate => (3, 3),
+        Op::Out => (1, 0),
+        Op::Index => (2, 1),
+        Op::ListLen | Op::Reverse | Op::First | Op::Last => (1, 1),
+        Op::ListPush | Op::Concat | Op::Contains | Op::IndexOf => (2, 1),
+        Op::ListPop => (1, 2),
+        Op::ListSet | Op::ListInsert | Op::Slice => (3, 1),
+        Op::ListRemove => (2, 2),
+        Op::Readln | Op::Args => (0, 1),
+        Op::Getenv => (1, 1),
+        Op::Exit => (1, 0),
+        Op::Readvar(_) => (0, 1),
+        Op::Writevar(_) => (1, 0),
+        _ => (0, 0),
+    }
+}
+
+struct Checker<'p> {
+    program: &'p Program,
+    words: HashMap<usize, (&'p str, &'p Word)>, // by body address
+    // inferred effect of every word, by body address. `None` while the word is being
+    // inferred (recursion) or when it can't be inferred
+    effects: HashMap<usize, Option<Effect>>,
+    errors: Vec<ForthError>,
+}
+
+// what came out of walking a piece of code
+struct Walk {
+    lowest: i64, // lowest depth reached, negative means values were taken from the caller
+    exit: Option<i64>, // depth at `return`
+    complete: bool, // false when some path called a word with an unknown effect
+}
+
+impl<'p> Checker<'p> {
+    fn new(program: &'p Program) -> Checker<'p> {
+        let words = program.words.iter().map(|(name, word)| (word.start, (name.as_str(), word))).collect();
+        Checker { program, words, effects: HashMap::new(), errors: vec![] }
+    }
+
+    fn effect_of(&mut self, start: usize) -> Option<Effect> {
+        if let Some(effect) = self.effects.get(&start) {
+            return *effect;
+        }
+        self.effects.insert(start, None); // a word calling itself can't be inferred
+        let walk = self.walk(start, 0, false);
+        let effect = match walk {
+            Walk { lowest, exit: Some(exit), complete: true } => {
+                let inputs = (-lowest).max(0);
+                Some(Effect { inputs: inputs as usize, outputs: (exit + inputs) as usize })
+            }
+            _ => None,
+        };
+        self.effects.insert(start, effect);
+        return effect;
+    }
+
+    // what calling the word at `start` does to the stack, a declared signature is taken at its word
+    fn call_effect(&mut self, start: usize) -> Option<Effect> {
+        if let Some((_, Word { sig: Some(sig), .. })) = self.words.get(&start) {
+            return Some(sig.effect());
+        }
+        return self.effect_of(start);
+    }
+
+    // the body of a word has to do what its signature says. a body that leaves some inputs
+    // untouched is fine, `( a b -- a b c )` can be implemented by something that only pushes.
+    // returns false when they don't agree
+    fn check_signature(&mut self, name: &str, word: &Word, sig: &Signature) -> bool {
+        let inferred = match self.effect_of(word.start) {
+            Some(effect) => effect,
+            None => return true, // nothing to compare against
+        };
+        let declared = sig.effect();
+        let net = |e: Effect| e.outputs as i64 - e.inputs as i64;
+        if declared.inputs >= inferred.inputs && net(declared) == net(inferred) {
+            return true;
+        }
+        let msg = format!("stack effect of `{}` doesn't match its declaration", name);
+        let note = format!("the body takes {} value(s) off the stack and leaves {}", inferred.inputs, inferred.outputs);
+        let e = ForthError::parse(word.span, msg)
+            .with_label(format!("declared as {}", sig))
+            .with_note(None, note);
+        self.errors.push(e);
+        return false;
+    }
+
+    // what `op` does to the types on the stack, `None` when it can't be followed any further
+    fn apply_types(&mut self, op: &Op, stack: &mut Vec<Ty>) -> Option<()> {
+        let (pops, pushes) = match op {
+            Op::Call(label) => {
+                let effect = self.call_effect(*label)?;
+                (effect.inputs, effect.outputs)
+            }
+            Op::Native(n) => {
+                let effect = self.program.natives[*n].effect;
+                (effect.inputs, effect.outputs)
+            }
+            // how many values it takes depends on a number only known when it runs
+            Op::ToList => return None,
+            Op::If(_) | Op::Ifstar(_) | Op::Do(_) => (1, 0),
+            op => simple_effect(op),
+        };
+        if stack.len() < pops {
+            return None; // the depth check already complained about this
+        }
+        let popped = stack.split_off(stack.len() - pops);
+        match op {
+            Op::PushInteger(_) | Op::PushFloat(_) => stack.push(Ty::Number),
+            Op::PushStr(_) => stack.push(Ty::Str),
+            Op::PushBool(_) => stack.push(Ty::Boolean),
+            Op::PushList(_) | Op::Args => stack.push(Ty::List),
+            Op::ListPush | Op::ListSet | Op::ListInsert | Op::Concat | Op::Slice | Op::Reverse => stack.push(Ty::List),
+            Op::ListPop | Op::ListRemove => stack.extend([Ty::List, Ty::Any]),
+            Op::ListLen => stack.push(Ty::Number),
+            Op::Contains => stack.push(Ty::Boolean),
+            Op::Add | Op::Sub | Op::Mul | Op::Div | Op::Floor => stack.push(Ty::Number),
+            Op::Eq | Op::Gt | Op::Lt | Op::Gteq | Op::Lteq => stack.push(Ty::Boolean),
+            Op::Dup => stack.extend([popped[0], popped[0]]),
+            Op::Swap => stack.extend([popped[1], popped[0]]),
+            Op::Over => stack.extend([popped[0], popped[1], popped[0]]),
+            Op::Rotate => stack.extend([popped[1], popped[2], popped[0]]),
+            Op::Call(label) => match self.words.get(label) {
+                Some((_, Word { sig: Some(sig), .. })) => stack.extend(sig.output_types()),
+                _ => stack.extend(std::iter::repeat_n(Ty::Any, pushes)),
+            },
+            _ => stack.extend(std::iter::repeat_n(Ty::Any, pushes)),
+        }
+        return Some(());
+    }
+
+    // types a call or a primitive expects to find on the stack, top first
+    fn expected_types(&self, op: &Op) -> Vec<Ty> {
+        match op {
+            Op::Call(label) => match self.words.get(label) {
+                Some((_, Word { sig: Some(sig), .. })) => sig.input_types().into_iter().rev().collect(),
+                _ => vec![],
+            },
+            op => operand_types(op),
+        }
+    }
+
+    // follow the body of a word starting from the types its signature declares. types coming
+    // together from different paths get widened to `Any`, so only mistakes that happen no
+    // matter which branch was taken get reported
+    fn check_types(&mut self, name: &str, word: &Word, sig: &Signature) {
+        let ops = &self.program.ops;
+        let mut states: HashMap<usize, Vec<Ty>> = HashMap::new();
+        let mut todo: Vec<(usize, Vec<Ty>)> = vec![(word.start, sig.input_types())];
+
+        while let Some((ip, mut stack)) = todo.pop() {
+            if let Some(prev) = states.get(&ip) {
+                if prev.len() != stack.len() {
+                    continue; // depth problem, reported by `walk`
+                }
+                let joined: Vec<Ty> = prev.iter().zip(&stack).map(|(a, b)| a.join(*b)).collect();
+                if &joined == prev {
+                    continue;
+                }
+                stack = joined;
+            }
+            states.insert(ip, stack.clone());
+
+            let mut next = stack;
+            if let Op::EndList(open) = ops[ip] {
+                // everything above the depth at the `{` goes into the list
+                let mark = states.get(&open).map_or(0, |s| s.len()).min(next.len());
+                next.truncate(mark);
+                next.push(Ty::List);
+            } else if self.apply_types(&ops[ip], &mut next).is_none() {
+                continue;
+            }
+            match ops[ip] {
+                Op::If(label) | Op::Ifstar(label) | Op::Do(label) => {
+                    todo.push((label, next.clone()));
+                    todo.push((ip+1, next));
+                }
+                Op::Else(label) | Op::End(label) | Op::Defword(label) => todo.push((label, next)),
+                // nothing runs after `exit` and `bye`
+                Op::Return | Op::Exit | Op::Bye | Op::EndOfProgram => (),
+                _ => todo.push((ip+1, next)),
+            }
+        }
+
+        // now that every state is as wide as it gets, look for mismatches
+        let mut addresses: Vec<usize> = states.keys().cloned().collect();
+        addresses.sort();
+        let mut reported: HashSet<usize> = HashSet::new();
+        let declared_as = format!("`{}` is declared as {}", name, sig);
+        for ip in addresses {
+            let stack = &states[&ip];
+            if let Op::Return = ops[ip] {
+                let outputs = sig.output_types();
+                let actual = &stack[stack.len().saturating_sub(outputs.len())..];
+                for (expected, got) in outputs.iter().zip(actual) {
+                    if !got.fits(*expected) {
+                        let msg = format!("`{}` should leave {} on the stack, but leaves {}", name, expected, got);
+                        let e = ForthError::parse(self.span(ip), msg).with_note(Some(word.span), declared_as.clone());
+                        self.errors.push(e);
+                        break;
+                    }
+                }
+                continue;
+            }
+            for (k, expected) in self.expected_types(&ops[ip]).into_iter().enumerate() {
+                let got = match stack.len().checked_sub(k+1) {
+                    Some(n) => stack[n],
+                    None => break,
+                };
+                if !got.fits(expected) && reported.insert(ip) {
+                    let msg = format!("`{}` expects {}, but gets {}", self.program.locs[ip].word, expected, got);
+                    let e = ForthError::parse(self.span(ip), msg)
+                        .with_label(format!("wrong type for value {} from the top of the stack", k+1))
+                        .with_note(Some(word.span), declared_as.clone());
+                    self.errors.push(e);
+                }
+            }
+        }
+    }
+
+    fn span(&self, ip: usize) -> Span {
+        self.program.locs[ip].span
+    }
+
+    // two paths reached `ip` with a different number of values on the stack
+    fn mismatch(&mut self, ip: usize, from: usize, first: i64, second: i64) {
+        let e = match self.program.ops[ip] {
+            Op::While => {
+                let diff = second - first;
+                let label = if diff > 0 {
+                    format!("every iteration leaves {} more value(s) on the stack", diff)
+                } else {
+                    format!("every iteration takes {} value(s) off the stack", -diff)
+                };
+                ForthError::parse(self.span(ip), "`while` loop body changes the stack depth")
+                    .with_label(label)
+                    .with_note(Some(self.span(from)), "loop jumps back from here")
+            }
+            _ => {
+                let label = format!("one branch leaves {} more value(s) than the other", (second - first).abs());
+                ForthError::parse(self.span(ip), "branches leave the stack at different depths")
+                    .with_label(label)
+                    .with_note(None, "every branch of an `if` has to leave the same number of values on the stack")
+            }
+        };
+        self.errors.push(e);
+    }
+
+    fn underflow(&mut self, ip: usize, needs: usize, has: i64) {
+        let word = &self.program.locs[ip].word;
+        let msg = format!("stack underflow, `{}` needs {} value(s)", word, needs);
+        let label = format!("only {} value(s) on the stack here", has);
+        self.errors.push(ForthError::parse(self.span(ip), msg).with_label(label));
+    }
+
+    // follow every path from `start`. top level code starts with `depth` values on the stack and
+    // can't go below empty, word bodies start at 0 and going negative means taking inputs
+    fn walk(&mut self, start: usize, depth: i64, top_level: bool) -> Walk {
+        let mut seen: HashMap<usize, i64> = HashMap::new();
+        let mut todo: Vec<(usize, i64, usize)> = vec![(start, depth, start)]; // (ip, depth, came from)
+        let mut walk = Walk { lowest: depth, exit: None, complete: true };
+
+        while let Some((ip, d, from)) = todo.pop() {
+            if let Some(&prev) = seen.get(&ip) {
+                if prev != d {
+                    self.mismatch(ip, from, prev, d);
+                }
+                continue;
+            }
+            seen.insert(ip, d);
+
+            let (pops, pushes) = match self.program.ops[ip] {
+                Op::Call(label) => match self.call_effect(label) {
+                    Some(effect) => (effect.inputs, effect.outputs),
+                    None => {
+                        walk.complete = false;
+                        continue;
+                    }
+                },
+                Op::Native(n) => {
+                    let effect = self.program.natives[n].effect;
+                    (effect.inputs, effect.outputs)
+                }
+                // how many values it takes is only known when it runs, but the count has to be there
+                Op::ToList => {
+                    if top_level && d < 1 {
+                        self.underflow(ip, 1, d);
+                    }
+                    walk.lowest = walk.lowest.min(d - 1);
+                    walk.complete = false;
+                    continue;
+                }
+                // everything pushed since the `{`, which was walked on the way here
+                Op::EndList(open) => {
+                    let mark = seen.get(&open).copied().unwrap_or(d);
+                    if d < mark {
+                        let e = ForthError::parse(self.span(ip), "list takes values from outside of `{ }`")
+                            .with_label(format!("{} value(s) from before the `{{` get used up", mark - d));
+                        self.errors.push(e);
+                    }
+                    ((d - mark).max(0) as usize, 1)
+                }
+                Op::If(_) | Op::Ifstar(_) | Op::Do(_) => (1, 0),
+                ref op => simple_effect(op),
+            };
+            let after_pop = d - pops as i64;
+            if top_level && after_pop < 0 {
+                self.underflow(ip, pops, d);
+                continue; // anything after this is nonsense
+            }
+            walk.lowest = walk.lowest.min(after_pop);
+            let d = after_pop + pushes as i64;
+
+            match self.program.ops[ip] {
+                Op::If(label) | Op::Ifstar(label) | Op::Do(label) => {
+                    todo.push((label, d, ip));
+                    todo.push((ip+1, d, ip));
+                }
+                Op::Else(label) | Op::End(label) | Op::Defword(label) => todo.push((label, d, ip)),
+                Op::Return => walk.exit = Some(d),
+                // nothing runs after `exit` and `bye`
+                Op::Exit | Op::Bye | Op::EndOfProgram => (),
+                _ => todo.push((ip+1, d, ip)),
+            }
+        }
+        return walk;
+    }
+}
+
+// check the code compiled from `start` onwards as if it was run with `depth` values already on the stack
+pub(crate) fn check(program: &Program, start: usize, depth: usize) -> Result<(), ForthError> {
+    let mut checker = Checker::new(program);
+
+    // every new word gets checked, even the ones that are never called
+    let mut starts: Vec<usize> = program.words.values().map(|w| w.start).filter(|&s| s >= start).collect();
+    starts.sort();
+    for start in starts {
+        let (name, word) = checker.words[&start];
+        let errors_before = checker.errors.len();
+        checker.effect_of(start);
+        if let Some(sig) = &word.sig {
+            // types only make sense once the depths are right
+            if checker.check_signature(name, word, sig) && checker.errors.len() == errors_before && sig.is_typed() {
+                checker.check_types(name, word, sig);
+            }
+        }
+    }
+    checker.walk(start, depth as i64, true);
+
+    if checker.errors.is_empty() {
+        return Ok(());
+    }
+    let mut errors = checker.errors;
+    errors.sort_by_key(|e| e.span().map(|s| (s.line, s.col)));
+    return Err(ForthError::from_errors(errors));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parse_to_program, tokenize};
+
+    fn compile(source: &str) -> Program {
+        let tokens = tokenize(source, 0).unwrap();
+        let mut program = Program::default();
+        parse_to_program(&tokens, &mut program, 0).unwrap();
+        return program;
+    }
+
+    // the messages of everything the checker finds wrong with `source`, run on an empty stack
+    fn errors(source: &str) -> Vec<String> {
+        let program = compile(source);
+        match check(&program, 0, 0) {
+            Ok(()) => return vec![],
+            Err(ForthError::Multiple(errors)) => return errors.iter().map(|e| String::from(e.message())).collect(),
+            Err(e) => return vec![String::from(e.message())],
+        }
+    }
+
+    #[test]
+    fn nothing_runs_after_exit_or_bye() {
+        assert!(errors("args len 0 = if \"usage\" out 2 exit else args first end out").is_empty());
+        assert!(errors("bye drop").is_empty());
+        assert!(errors("1 exit drop drop").is_empty());
+        assert!(errors("defword w ( n -- ) if 1 exit else 2 end drop return 1 w").is_empty());
+        assert_eq!(errors("exit"), ["stack underflow, `exit` needs 1 value(s)"]);
+    }
+
+    // what the checker works out `name` in `source` does to the stack
+    fn effect(source: &str, name: &str) -> Option<Effect> {
+        let program = compile(source);
+        return Checker::new(&program).effect_of(program.words[name].start);
+    }
+
+    #[test]
+    fn branches_have_to_agree() {
+        assert_eq!(errors("1 if 1 else end"), ["branches leave the stack at different depths"]);
+        assert_eq!(errors("1 if 1 2 else 3 if* 4 else 5 end")[0], "branches leave the stack at different depths");
+        assert!(errors("1 if 1 else 2 end out").is_empty());
+        assert!(errors("1 if 1 else 0 if* 2 else 3 end out").is_empty());
+    }
+
+    #[test]
+    fn loops_have_to_leave_the_stack_as_it_was() {
+        assert_eq!(errors("while 1 do 1 end"), ["`while` loop body changes the stack depth"]);
+        assert_eq!(errors("1 2 3 while 1 do drop end"), ["`while` loop body changes the stack depth"]);
+        assert!(errors("0 while dup 10 < do 1 + end drop").is_empty());
+    }
+
+    #[test]
+    fn top_level_underflow() {
+        assert_eq!(errors("1 +"), ["stack underflow, `+` needs 2 value(s)"]);
+        assert_eq!(errors("drop"), ["stack underflow, `drop` needs 1 value(s)"]);
+        assert_eq!(errors("1 2 rotate"), ["stack underflow, `rotate` needs 3 value(s)"]);
+        assert!(errors("1 2 3 rotate drop drop drop").is_empty());
+    }
+
+    #[test]
+    fn inferred_effects() {
+        assert_eq!(effect("defword two 1 2 return", "two"), Some(Effect { inputs: 0, outputs: 2 }));
+        assert_eq!(effect("defword add3 + + return", "add3"), Some(Effect { inputs: 3, outputs: 1 }));
+        assert_eq!(effect("defword keep over over + return", "keep"), Some(Effect { inputs: 2, outputs: 3 }));
+        assert_eq!(errors("defword add3 + + return 1 2 add3"), ["stack underflow, `add3` needs 3 value(s)"]);
+        assert!(errors("defword two 1 2 return defword add3 + + return 1 two add3 out").is_empty());
+    }
+
+    #[test]
+    fn recursive_words_need_a_signature() {
+        // a word calling itself can't be inferred, so its callers can't be checked past the call
+        assert_eq!(effect("defword count dup if 1 - count end return", "count"), None);
+        assert!(errors("defword count dup if 1 - count end return count drop drop").is_empty());
+        // with a signature the call is taken at its word
+        let source = "defword count ( n -- n ) dup if 1 - count end return";
+        assert!(errors(source).is_empty());
+        assert_eq!(errors(&format!("{} count", source)), ["stack underflow, `count` needs 1 value(s)"]);
+    }
+
+    #[test]
+    fn built_lists() {
+        assert!(errors("{ 1 2 3 } len out").is_empty());
+        assert!(errors("1 { } drop drop").is_empty());
+        assert_eq!(errors("{ 1 2 } drop drop"), ["stack underflow, `drop` needs 1 value(s)"]);
+        assert_eq!(errors("1 2 { + }"), ["list takes values from outside of `{ }`"]);
+        assert_eq!(effect("defword pair { 1 2 } return", "pair"), Some(Effect { inputs: 0, outputs: 1 }));
+        assert_eq!(errors("defword sum { + } return"), ["list takes values from outside of `{ }`"]);
+    }
+
+    #[test]
+    fn to_list_takes_a_count_only_known_at_runtime() {
+        assert!(errors("1 2 2 >list drop drop drop").is_empty());
+        assert_eq!(effect("defword wrap 1 >list return", "wrap"), None);
+        assert_eq!(errors(">list"), ["stack underflow, `>list` needs 1 value(s)"]);
+    }
+
+    #[test]
+    fn bodies_have_to_match_their_signature() {
+        assert_eq!(errors("defword w ( a -- b c ) drop return"), ["stack effect of `w` doesn't match its declaration"]);
+        assert_eq!(errors("defword w ( a b -- c ) return"), ["stack effect of `w` doesn't match its declaration"]);
+        // inputs the body doesn't touch are passed through
+        assert!(errors("defword w ( a b -- a b c ) 1 return").is_empty());
+        assert!(errors("defword w ( a b -- c ) + return").is_empty());
+    }
+
+    #[test]
+    fn typed_signatures() {
+        assert_eq!(errors("defword w ( n -- s ) 1 + return"), ["`w` should leave Str on the stack, but leaves Number"]);
+        assert_eq!(errors("defword w ( s -- n ) 1 + return"), ["`+` expects Number, but gets Str"]);
+        assert_eq!(errors("defword w ( s -- n ) len return"), ["`len` expects List, but gets Str"]);
+        assert!(errors("defword w ( list -- n ) first return").is_empty());
+        // types from different branches are widened, only mistakes made whichever way it went are reported
+        assert!(errors("defword w ( n -- n ) if 1 else \"a\" end 1 + return").is_empty());
+    }
+}
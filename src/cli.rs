@@ -0,0 +1,181 @@
+// command line handling, see `USAGE`
+
+pub const USAGE: &str = "\
+usage: myforth [options] [repl]
+       myforth [options] [run] <file> [args...]
+       myforth [options] check <file>
+       myforth [options] -e <code> [args...]
+
+`-` as the file reads the program from stdin
+
+options:
+    --prelude <file>    run <file> before anything else, can be given more than once
+    --dump-tokens       print the tokens of everything that gets compiled
+    --dump-ops          print the compiled instructions
+    --trace             print every instruction and the stack as the program runs
+    --no-stack-trace    don't print the stack when the program is done
+    -h, --help          print this message
+
+exit status:
+    0    everything went fine
+    1    the program didn't compile or failed while running
+    2    bad command line or a file that can't be read
+";
+
+#[derive(Debug, PartialEq)]
+pub enum Mode {
+    Repl,
+    Run { file: String }, // `-` is stdin
+    Check { file: String },
+    Eval { code: String }, // `-e`
+    Help,
+}
+
+#[derive(Debug)]
+pub struct Options {
+    pub mode: Mode,
+    pub args: Vec<String>, // whatever comes after the file or the `-e` code, for the program itself
+    pub preludes: Vec<String>,
+    pub dump_tokens: bool,
+    pub dump_ops: bool,
+    pub trace: bool,
+    pub stack_trace: bool,
+}
+
+// `args` without the name of the executable. the error is a message for the user
+pub fn parse_args(args: Vec<String>) -> Result<Options, String> {
+    let mut options = Options {
+        mode: Mode::Repl,
+        args: vec![],
+        preludes: vec![],
+        dump_tokens: false,
+        dump_ops: false,
+        trace: false,
+        stack_trace: true,
+    };
+    let mut code: Option<String> = None;
+    let mut positional: Vec<String> = vec![];
+
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        // everything after the program belongs to the program, flags included
+        let program_given = match positional.first().map(|p| p.as_str()) {
+            Some("run") => positional.len() >= 2,
+            Some("repl") | Some("check") => false,
+            Some(_) => true,
+            None => code.is_some(),
+        };
+        if program_given {
+            positional.push(arg);
+            continue;
+        }
+
+        match arg.as_str() {
+            "--prelude" => options.preludes.push(args.next().ok_or("`--prelude` expects a file name")?),
+            "-e" => code = Some(args.next().ok_or("`-e` expects some code")?),
+            "--dump-tokens" => options.dump_tokens = true,
+            "--dump-ops" => options.dump_ops = true,
+            "--trace" => options.trace = true,
+            "--no-stack-trace" => options.stack_trace = false,
+            "-h" | "--help" => options.mode = Mode::Help,
+            "-" => positional.push(arg),
+            flag if flag.starts_with('-') => return Err(format!("unknown option `{}`", flag)),
+            _ => positional.push(arg),
+        }
+    }
+    if options.mode == Mode::Help {
+        return Ok(options);
+    }
+
+    if let Some(code) = code {
+        options.mode = Mode::Eval { code };
+        options.args = positional;
+        return Ok(options);
+    }
+    let mut positional = positional.into_iter();
+    options.mode = match positional.next().as_deref() {
+        None | Some("repl") => Mode::Repl,
+        Some("run") => Mode::Run { file: positional.next().ok_or("`run` expects a file")? },
+        Some("check") => Mode::Check { file: positional.next().ok_or("`check` expects a file")? },
+        Some(file) => Mode::Run { file: String::from(file) },
+    };
+    options.args = positional.collect();
+    if !options.args.is_empty() && matches!(options.mode, Mode::Repl | Mode::Check { .. }) {
+        return Err(format!("unexpected argument `{}`", options.args[0]));
+    }
+    return Ok(options);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Options, String> {
+        parse_args(args.iter().map(|a| String::from(*a)).collect())
+    }
+
+    fn run(file: &str) -> Mode {
+        Mode::Run { file: String::from(file) }
+    }
+
+    #[test]
+    fn modes() {
+        assert_eq!(parse(&[]).unwrap().mode, Mode::Repl);
+        assert_eq!(parse(&["repl"]).unwrap().mode, Mode::Repl);
+        assert_eq!(parse(&["prog.forth"]).unwrap().mode, run("prog.forth"));
+        assert_eq!(parse(&["run", "prog.forth"]).unwrap().mode, run("prog.forth"));
+        assert_eq!(parse(&["check", "prog.forth"]).unwrap().mode, Mode::Check { file: String::from("prog.forth") });
+        assert_eq!(parse(&["-e", "1 out"]).unwrap().mode, Mode::Eval { code: String::from("1 out") });
+    }
+
+    #[test]
+    fn a_dash_is_stdin() {
+        assert_eq!(parse(&["-"]).unwrap().mode, run("-"));
+        assert_eq!(parse(&["run", "-", "a"]).unwrap().args, ["a"]);
+        assert_eq!(parse(&["check", "-"]).unwrap().mode, Mode::Check { file: String::from("-") });
+    }
+
+    #[test]
+    fn everything_after_the_program_belongs_to_it() {
+        let options = parse(&["--trace", "prog.forth", "--trace", "-e", "x", "-"]).unwrap();
+        assert_eq!(options.mode, run("prog.forth"));
+        assert_eq!(options.args, ["--trace", "-e", "x", "-"]);
+        assert!(options.trace);
+
+        let options = parse(&["run", "repl", "--help"]).unwrap();
+        assert_eq!(options.mode, run("repl"));
+        assert_eq!(options.args, ["--help"]);
+
+        let options = parse(&["-e", "args out", "a", "--no-stack-trace"]).unwrap();
+        assert_eq!(options.mode, Mode::Eval { code: String::from("args out") });
+        assert_eq!(options.args, ["a", "--no-stack-trace"]);
+        assert!(options.stack_trace);
+    }
+
+    #[test]
+    fn options() {
+        let options = parse(&["--prelude", "a", "--prelude", "b", "--dump-tokens", "--dump-ops", "--no-stack-trace"]).unwrap();
+        assert_eq!(options.preludes, ["a", "b"]);
+        assert!(options.dump_tokens && options.dump_ops && !options.stack_trace && !options.trace);
+        assert_eq!(options.mode, Mode::Repl);
+    }
+
+    #[test]
+    fn help_wins() {
+        assert_eq!(parse(&["--help"]).unwrap().mode, Mode::Help);
+        assert_eq!(parse(&["-h", "check"]).unwrap().mode, Mode::Help);
+        assert_eq!(parse(&["repl", "extra", "--help"]).unwrap().mode, Mode::Help);
+        assert_eq!(parse(&["run", "--help"]).unwrap().mode, Mode::Help);
+    }
+
+    #[test]
+    fn mistakes() {
+        assert_eq!(parse(&["repl", "extra"]).unwrap_err(), "unexpected argument `extra`");
+        assert_eq!(parse(&["check", "a", "b"]).unwrap_err(), "unexpected argument `b`");
+        assert_eq!(parse(&["run"]).unwrap_err(), "`run` expects a file");
+        assert_eq!(parse(&["check"]).unwrap_err(), "`check` expects a file");
+        assert_eq!(parse(&["-e"]).unwrap_err(), "`-e` expects some code");
+        assert_eq!(parse(&["--prelude"]).unwrap_err(), "`--prelude` expects a file name");
+        assert_eq!(parse(&["--bogus"]).unwrap_err(), "unknown option `--bogus`");
+    }
+}
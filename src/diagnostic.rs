@@ -0,0 +1,156 @@
+// renders errors the way rustc does, with the offending line of source and a caret under the token:
+//
+// error: Unknown Word `foo` Encountered
+//  --> example.forth:3:5
+//   |
+// 3 | 1 2 foo
+//   |     ^^^ not defined
+//   |
+//   = note: words have to be declared with `defword` before they are used
+
+use crate::error::*;
+
+// a piece of source code that was evaluated, `Span::source` is an index into a list of these
+#[derive(Debug, Clone)]
+pub struct Source {
+    pub name: String, // file name or something like `<repl>`
+    pub text: String,
+}
+
+const RED: &str = "\x1b[1;31m";
+const BLUE: &str = "\x1b[1;34m";
+const BOLD: &str = "\x1b[1m";
+const RESET: &str = "\x1b[0m";
+
+struct Renderer<'a> {
+    sources: &'a [Source],
+    color: bool,
+    out: String,
+}
+
+impl<'a> Renderer<'a> {
+    fn paint(&self, style: &str, text: &str) -> String {
+        if self.color {
+            format!("{}{}{}", style, text, RESET)
+        } else {
+            text.to_string()
+        }
+    }
+
+    fn header(&mut self, kind: &str, style: &str, msg: &str) {
+        let line = format!("{}{}\n", self.paint(style, kind), self.paint(BOLD, &format!(": {}", msg)));
+        self.out.push_str(&line);
+    }
+
+    // the ` --> file:line:col` line, the source line and the underline
+    fn file(&self, span: Span) -> &'a str {
+        self.sources.get(span.source).map_or("<unknown>", |s| s.name.as_str())
+    }
+
+    fn snippet(&mut self, span: Span, underline: char, style: &str, label: Option<&str>, gutter: usize) {
+        let pad = " ".repeat(gutter);
+        let arrow = self.paint(BLUE, "-->");
+        self.out.push_str(&format!("{}{} {}:{}:{}\n", pad, arrow, self.file(span), span.line, span.col));
+
+        let bar = self.paint(BLUE, "|");
+        let source = self.sources.get(span.source).map_or("", |s| s.text.as_str());
+        let text = match span.line.checked_sub(1).and_then(|n| source.lines().nth(n)) {
+            Some(text) => text,
+            None => return, // the span doesn't belong to this source, location only
+        };
+        self.out.push_str(&format!("{} {}\n", pad, bar));
+        let number = self.paint(BLUE, &format!("{:>width$}", span.line, width = gutter));
+        self.out.push_str(&format!("{} {} {}\n", number, bar, text));
+
+        // keep tabs so the underline lines up with the source line
+        let indent: String = text.chars()
+                                 .take(span.col.saturating_sub(1))
+                                 .map(|c| if c == '\t' { '\t' } else { ' ' })
+                                 .collect();
+        let mut marks = underline.to_string().repeat(span.len.max(1));
+        if let Some(label) = label {
+            marks.push(' ');
+            marks.push_str(label);
+        }
+        self.out.push_str(&format!("{} {} {}{}\n", pad, bar, indent, self.paint(style, &marks)));
+    }
+
+    fn note(&mut self, msg: &str, gutter: usize) {
+        let pad = " ".repeat(gutter);
+        let eq = self.paint(BLUE, "=");
+        self.out.push_str(&format!("{} {} {}: {}\n", pad, eq, self.paint(BOLD, "note"), msg));
+    }
+
+    fn error(&mut self, e: &ForthError) {
+        if let ForthError::Multiple(errors) = e {
+            for e in errors {
+                self.error(e);
+                self.out.push('\n');
+            }
+            let msg = format!("aborting due to {} previous errors", errors.len());
+            self.header("error", RED, &msg);
+            return;
+        }
+
+        self.header("error", RED, e.message());
+        let span = match e.span() {
+            Some(span) => span,
+            None => return,
+        };
+
+        let (label, notes) = match e {
+            ForthError::Lex { label, notes, .. } | ForthError::Parse { label, notes, .. } => {
+                (label.clone(), notes.clone())
+            }
+            ForthError::Runtime { word, backtrace, stack, .. } => {
+                let mut notes: Vec<Note> = vec![];
+                for frame in backtrace {
+                    let site = frame.call_site;
+                    let msg = format!("in `{}` called at {}:{}:{}", frame.word, self.file(site), site.line, site.col);
+                    notes.push(Note { span: None, msg });
+                }
+                let values: Vec<String> = stack.iter().map(|v| format!("{} ", v)).collect();
+                notes.push(Note { span: None, msg: format!("stack: [ {}]", values.concat()) });
+                (word.as_ref().map(|w| format!("`{}` failed here", w)), notes)
+            }
+            ForthError::Multiple(_) => unreachable!(),
+        };
+
+        // wide enough for every line number that gets printed
+        let widest = notes.iter()
+                          .filter_map(|n| n.span)
+                          .map(|s| s.line)
+                          .fold(span.line, usize::max);
+        let gutter = widest.to_string().len();
+
+        self.snippet(span, '^', RED, label.as_deref(), gutter);
+        let pad = " ".repeat(gutter);
+        let bar = self.paint(BLUE, "|");
+        let mut after_note = false; // `= note:` lines are grouped together under a single `|`
+        for note in &notes {
+            match note.span {
+                Some(note_span) => {
+                    self.out.push_str(&format!("{} {}\n", pad, bar));
+                    let line = format!("{}{}\n", self.paint(BOLD, "note"), self.paint(BOLD, &format!(": {}", note.msg)));
+                    self.out.push_str(&line);
+                    self.snippet(note_span, '-', BLUE, None, gutter);
+                    after_note = false;
+                }
+                None => {
+                    if !after_note {
+                        self.out.push_str(&format!("{} {}\n", pad, bar));
+                    }
+                    self.note(&note.msg, gutter);
+                    after_note = true;
+                }
+            }
+        }
+    }
+}
+
+// `sources` is everything the spans in `e` can point into
+pub fn render(e: &ForthError, sources: &[Source], color: bool) -> String {
+    let mut renderer = Renderer { sources, color, out: String::new() };
+    renderer.error(e);
+    return renderer.out;
+}
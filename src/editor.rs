@@ -0,0 +1,519 @@
+// a small line editor for the repl: cursor movement, history that is kept in ~/.myforth_history,
+// reverse search with ctrl-r and tab completion. it puts the terminal in raw mode while a line is being typed,
+// when stdin isn't a terminal (piped input) it falls back to plain `read_line`
+
+use std::env;
+use std::fs::{self, OpenOptions};
+use std::io::{self, IsTerminal, Read, Write};
+use std::path::PathBuf;
+
+const HISTORY_FILE: &str = ".myforth_history";
+const HISTORY_SIZE: usize = 1000; // lines loaded from the history file
+
+// the layout and the constants below are the generic ones from glibc and musl, other architectures
+// (powerpc, mips, sparc, ...) use different ones and get the plain fallback
+#[cfg(all(
+    target_os = "linux",
+    any(target_env = "gnu", target_env = "musl"),
+    any(target_arch = "x86_64", target_arch = "x86", target_arch = "aarch64")
+))]
+mod termios {
+    use std::os::raw::{c_int, c_uchar, c_uint};
+
+    // `struct termios` from <termios.h>, the fields have to be in this order
+    #[repr(C)]
+    #[derive(Clone, Copy)]
+    #[allow(dead_code)]
+    struct Termios {
+        c_iflag: c_uint,
+        c_oflag: c_uint,
+        c_cflag: c_uint,
+        c_lflag: c_uint,
+        c_line: c_uchar,
+        c_cc: [c_uchar; 32],
+        c_ispeed: c_uint,
+        c_ospeed: c_uint,
+    }
+
+    extern "C" {
+        fn tcgetattr(fd: c_int, termios: *mut Termios) -> c_int;
+        fn tcsetattr(fd: c_int, when: c_int, termios: *const Termios) -> c_int;
+    }
+
+    const ICRNL: c_uint = 0o400; // turns the enter key into `\n`
+    const IXON: c_uint = 0o2000; // ctrl-s and ctrl-q flow control
+    const ISIG: c_uint = 0o1; // ctrl-c and ctrl-z send signals
+    const ICANON: c_uint = 0o2; // input is handed over a line at a time
+    const ECHO: c_uint = 0o10;
+    const IEXTEN: c_uint = 0o100000; // ctrl-v
+    const VTIME: usize = 5;
+    const VMIN: usize = 6;
+    const TCSAFLUSH: c_int = 2;
+    const STDIN: c_int = 0;
+
+    // the terminal hands over every key as soon as it is pressed and doesn't echo anything,
+    // it goes back to how it was when this is dropped
+    pub struct RawMode {
+        original: Termios,
+    }
+
+    impl RawMode {
+        pub fn enable() -> Option<RawMode> {
+            // SAFETY: `Termios` matches the C layout and both calls only touch the struct they're given
+            unsafe {
+                let mut original: Termios = std::mem::zeroed();
+                if tcgetattr(STDIN, &mut original) != 0 {
+                    return None;
+                }
+                let mut raw = original;
+                raw.c_iflag &= !(ICRNL | IXON);
+                raw.c_lflag &= !(ICANON | ECHO | ISIG | IEXTEN);
+                raw.c_cc[VMIN] = 1;
+                raw.c_cc[VTIME] = 0;
+                if tcsetattr(STDIN, TCSAFLUSH, &raw) != 0 {
+                    return None;
+                }
+                return Some(RawMode { original });
+            }
+        }
+    }
+
+    impl Drop for RawMode {
+        fn drop(&mut self) {
+            // SAFETY: see `enable`
+            unsafe {
+                tcsetattr(STDIN, TCSAFLUSH, &self.original);
+            }
+        }
+    }
+}
+
+#[cfg(not(all(
+    target_os = "linux",
+    any(target_env = "gnu", target_env = "musl"),
+    any(target_arch = "x86_64", target_arch = "x86", target_arch = "aarch64")
+)))]
+mod termios {
+    // no raw mode, lines are read the plain way
+    pub struct RawMode;
+
+    impl RawMode {
+        pub fn enable() -> Option<RawMode> {
+            None
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Key {
+    Char(char),
+    Ctrl(char), // ctrl + a letter
+    Enter,
+    Tab,
+    Backspace,
+    Delete,
+    Left,
+    Right,
+    Up,
+    Down,
+    Home,
+    End,
+    Unknown,
+}
+
+fn read_byte(input: &mut impl Read) -> io::Result<Option<u8>> {
+    let mut byte = [0u8; 1];
+    match input.read(&mut byte)? {
+        0 => return Ok(None),
+        _ => return Ok(Some(byte[0])),
+    }
+}
+
+// `None` when the input ran out
+fn read_key(input: &mut impl Read) -> io::Result<Option<Key>> {
+    let byte = match read_byte(input)? {
+        Some(byte) => byte,
+        None => return Ok(None),
+    };
+    let key = match byte {
+        b'\r' | b'\n' => Key::Enter,
+        b'\t' => Key::Tab,
+        127 | 8 => Key::Backspace,
+        27 => read_escape(input)?,
+        1..=26 => Key::Ctrl((b'a' + byte - 1) as char),
+        0..=31 => Key::Unknown,
+        _ => read_char(byte, input)?,
+    };
+    return Ok(Some(key));
+}
+
+// arrow keys and friends come in as `ESC [ x` or `ESC [ n ~`
+fn read_escape(input: &mut impl Read) -> io::Result<Key> {
+    match read_byte(input)? {
+        Some(b'[') | Some(b'O') => (),
+        _ => return Ok(Key::Unknown),
+    }
+    let key = match read_byte(input)? {
+        Some(b'A') => Key::Up,
+        Some(b'B') => Key::Down,
+        Some(b'C') => Key::Right,
+        Some(b'D') => Key::Left,
+        Some(b'H') => Key::Home,
+        Some(b'F') => Key::End,
+        Some(n @ b'0'..=b'9') => {
+            // the rest of the sequence has to be read either way
+            let mut code = vec![n];
+            while let Some(b) = read_byte(input)? {
+                if b == b'~' || !b.is_ascii_digit() {
+                    break;
+                }
+                code.push(b);
+            }
+            match code.as_slice() {
+                b"1" | b"7" => Key::Home,
+                b"4" | b"8" => Key::End,
+                b"3" => Key::Delete,
+                _ => Key::Unknown,
+            }
+        }
+        _ => Key::Unknown,
+    };
+    return Ok(key);
+}
+
+// the rest of a utf-8 encoded character that starts with `first`
+fn read_char(first: u8, input: &mut impl Read) -> io::Result<Key> {
+    let len = match first {
+        0xc0..=0xdf => 2,
+        0xe0..=0xef => 3,
+        0xf0..=0xf7 => 4,
+        _ => 1,
+    };
+    let mut bytes = vec![first];
+    while bytes.len() < len {
+        match read_byte(input)? {
+            Some(b) => bytes.push(b),
+            None => break,
+        }
+    }
+    match std::str::from_utf8(&bytes).ok().and_then(|s| s.chars().next()) {
+        Some(c) => return Ok(Key::Char(c)),
+        None => return Ok(Key::Unknown),
+    }
+}
+
+// the line being edited, `cursor` is an index into `chars`
+#[derive(Default)]
+struct Line {
+    chars: Vec<char>,
+    cursor: usize,
+}
+
+impl Line {
+    fn text(&self) -> String {
+        self.chars.iter().collect()
+    }
+
+    fn set(&mut self, text: &str) {
+        self.chars = text.chars().collect();
+        self.cursor = self.chars.len();
+    }
+
+    fn insert(&mut self, text: &str) {
+        for c in text.chars() {
+            self.chars.insert(self.cursor, c);
+            self.cursor += 1;
+        }
+    }
+
+    // where the word in front of the cursor starts
+    fn word_start(&self) -> usize {
+        let before = &self.chars[..self.cursor];
+        let end = before.iter().rposition(|c| !c.is_whitespace()).map_or(0, |i| i + 1);
+        return before[..end].iter().rposition(|c| c.is_whitespace()).map_or(0, |i| i + 1);
+    }
+
+    // redraw the whole line and put the cursor back where it belongs
+    fn draw(&self, prompt: &str) -> io::Result<()> {
+        let mut out = io::stdout();
+        write!(out, "\r{}{}\x1b[K", prompt, self.text())?;
+        let back = self.chars.len() - self.cursor;
+        if back > 0 {
+            write!(out, "\x1b[{}D", back)?;
+        }
+        return out.flush();
+    }
+}
+
+// the longest start that `a` and `b` have in common
+fn common_prefix<'a>(a: &'a str, b: &str) -> &'a str {
+    let len = a.char_indices()
+               .zip(b.chars())
+               .find(|((_, x), y)| x != y)
+               .map_or(a.len().min(b.len()), |((i, _), _)| i);
+    return &a[..len];
+}
+
+pub struct Editor {
+    history: Vec<String>,
+    path: Option<PathBuf>, // where the history is saved, there is none without a home directory
+}
+
+impl Editor {
+    pub fn new() -> Editor {
+        let path = env::var_os("HOME").map(|home| PathBuf::from(home).join(HISTORY_FILE));
+        let mut history: Vec<String> = match &path {
+            Some(path) => fs::read_to_string(path).unwrap_or_default().lines().map(String::from).collect(),
+            None => vec![],
+        };
+        let skip = history.len().saturating_sub(HISTORY_SIZE);
+        history.drain(..skip);
+        Editor { history, path }
+    }
+
+    // read a line without the newline, `None` at the end of the input.
+    // `words` are offered by tab completion. ctrl-c gives back an `Interrupted` error
+    pub fn read_line(&mut self, prompt: &str, words: &[String]) -> io::Result<Option<String>> {
+        if io::stdin().is_terminal() {
+            if let Some(_raw) = termios::RawMode::enable() {
+                let line = self.edit(prompt, words)?;
+                if let Some(line) = &line {
+                    self.remember(line);
+                }
+                return Ok(line);
+            }
+        }
+
+        print!("{}", prompt);
+        io::stdout().flush()?;
+        let mut line = String::new();
+        if io::stdin().read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        let len = line.trim_end_matches(['\n', '\r']).len();
+        line.truncate(len);
+        return Ok(Some(line));
+    }
+
+    // add a line to the history and the history file, the same line twice in a row only counts once
+    fn remember(&mut self, line: &str) {
+        if line.trim().is_empty() || self.history.last().map(|l| l.as_str()) == Some(line) {
+            return;
+        }
+        self.history.push(String::from(line));
+        // losing history isn't worth interrupting anyone for
+        if let Some(path) = &self.path {
+            if let Ok(mut file) = OpenOptions::new().create(true).append(true).open(path) {
+                let _ = writeln!(file, "{}", line);
+            }
+        }
+    }
+
+    fn edit(&mut self, prompt: &str, words: &[String]) -> io::Result<Option<String>> {
+        let mut input = io::stdin().lock();
+        let mut line = Line::default();
+        // where we are when going through the history, `history.len()` is the line being typed
+        let mut place = self.history.len();
+        let mut typed = String::new(); // the line being typed, while looking at the history
+        line.draw(prompt)?;
+        loop {
+            let key = match read_key(&mut input)? {
+                Some(key) => key,
+                None => return Ok(None),
+            };
+            match key {
+                Key::Enter => {
+                    print!("\r\n");
+                    return Ok(Some(line.text()));
+                }
+                Key::Ctrl('d') if line.chars.is_empty() => {
+                    print!("\r\n");
+                    return Ok(None);
+                }
+                Key::Ctrl('c') => {
+                    print!("^C\r\n");
+                    return Err(io::Error::from(io::ErrorKind::Interrupted));
+                }
+                Key::Char(c) => line.insert(&c.to_string()),
+                Key::Backspace if line.cursor > 0 => {
+                    line.cursor -= 1;
+                    line.chars.remove(line.cursor);
+                }
+                Key::Delete | Key::Ctrl('d') if line.cursor < line.chars.len() => {
+                    line.chars.remove(line.cursor);
+                }
+                Key::Left | Key::Ctrl('b') => line.cursor = line.cursor.saturating_sub(1),
+                Key::Right | Key::Ctrl('f') => line.cursor = (line.cursor + 1).min(line.chars.len()),
+                Key::Home | Key::Ctrl('a') => line.cursor = 0,
+                Key::End | Key::Ctrl('e') => line.cursor = line.chars.len(),
+                Key::Ctrl('k') => line.chars.truncate(line.cursor),
+                Key::Ctrl('u') => {
+                    line.chars.drain(..line.cursor);
+                    line.cursor = 0;
+                }
+                Key::Ctrl('w') => {
+                    let start = line.word_start();
+                    line.chars.drain(start..line.cursor);
+                    line.cursor = start;
+                }
+                Key::Ctrl('l') => print!("\x1b[H\x1b[2J"),
+                Key::Up | Key::Ctrl('p') if place > 0 => {
+                    if place == self.history.len() {
+                        typed = line.text();
+                    }
+                    place -= 1;
+                    line.set(&self.history[place]);
+                }
+                Key::Down | Key::Ctrl('n') if place < self.history.len() => {
+                    place += 1;
+                    match self.history.get(place) {
+                        Some(entry) => line.set(entry),
+                        None => line.set(&typed),
+                    }
+                }
+                Key::Tab => self.complete(&mut line, words)?,
+                Key::Ctrl('r') => {
+                    let picked = self.search(&mut input, &mut line)?;
+                    if picked {
+                        line.draw(prompt)?;
+                        print!("\r\n");
+                        return Ok(Some(line.text()));
+                    }
+                }
+                _ => (),
+            }
+            line.draw(prompt)?;
+        }
+    }
+
+    // finish the word in front of the cursor. with more than one candidate it gets filled in
+    // as far as they all agree, if that doesn't add anything they are listed instead
+    fn complete(&self, line: &mut Line, words: &[String]) -> io::Result<()> {
+        let start = line.chars[..line.cursor].iter().rposition(|c| c.is_whitespace()).map_or(0, |i| i + 1);
+        let prefix: String = line.chars[start..line.cursor].iter().collect();
+        if prefix.is_empty() {
+            return Ok(());
+        }
+        let matches: Vec<&str> = words.iter().map(|w| w.as_str()).filter(|w| w.starts_with(&prefix)).collect();
+        match matches.as_slice() {
+            [] => (),
+            [word] => {
+                line.insert(&word[prefix.len()..]);
+                line.insert(" ");
+            }
+            [first, rest @ ..] => {
+                let common = rest.iter().fold(*first, |common, w| common_prefix(common, w));
+                if common.len() > prefix.len() {
+                    line.insert(&common[prefix.len()..]);
+                } else {
+                    print!("\r\n{}\r\n", matches.join("  "));
+                }
+            }
+        }
+        return Ok(());
+    }
+
+    // ctrl-r: go back through the history for lines containing what's typed after it.
+    // ctrl-r again finds the next older one, enter runs the line that was found (returns true),
+    // any other key keeps it for editing and ctrl-g or ctrl-c leave the line alone
+    fn search(&self, input: &mut impl Read, line: &mut Line) -> io::Result<bool> {
+        let mut query = String::new();
+        let mut found: Option<usize> = None;
+        let mut failed = false;
+        loop {
+            let shown = found.map_or("", |i| self.history[i].as_str());
+            let status = if failed { "failed reverse-i-search" } else { "reverse-i-search" };
+            print!("\r({})`{}': {}\x1b[K", status, query, shown);
+            io::stdout().flush()?;
+
+            // only lines older than this are searched
+            let before = match read_key(input)? {
+                Some(Key::Char(c)) => {
+                    query.push(c);
+                    found.map_or(self.history.len(), |i| i + 1) // the current match may still fit
+                }
+                Some(Key::Backspace) => {
+                    query.pop();
+                    self.history.len()
+                }
+                Some(Key::Ctrl('r')) => found.unwrap_or(self.history.len()),
+                Some(Key::Ctrl('g')) | Some(Key::Ctrl('c')) | None => return Ok(false),
+                Some(key) => {
+                    if let Some(i) = found {
+                        line.set(&self.history[i]);
+                    }
+                    return Ok(key == Key::Enter);
+                }
+            };
+            if query.is_empty() {
+                found = None;
+                failed = false;
+                continue;
+            }
+            match self.history[..before].iter().rposition(|entry| entry.contains(&query)) {
+                Some(i) => {
+                    found = Some(i);
+                    failed = false;
+                }
+                None => failed = true,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // every key in `bytes`
+    fn keys(mut bytes: &[u8]) -> Vec<Key> {
+        let mut keys = vec![];
+        while let Some(key) = read_key(&mut bytes).unwrap() {
+            keys.push(key);
+        }
+        return keys;
+    }
+
+    fn line(text: &str, cursor: usize) -> Line {
+        Line { chars: text.chars().collect(), cursor }
+    }
+
+    #[test]
+    fn plain_keys() {
+        assert_eq!(keys(b"a\r\n\t"), [Key::Char('a'), Key::Enter, Key::Enter, Key::Tab]);
+        assert_eq!(keys(b"\x7f\x08"), [Key::Backspace, Key::Backspace]);
+        assert_eq!(keys(b"\x01\x12\x00"), [Key::Ctrl('a'), Key::Ctrl('r'), Key::Unknown]);
+        assert_eq!(keys("é∅".as_bytes()), [Key::Char('é'), Key::Char('∅')]);
+        assert_eq!(keys(b"\xe2\x88"), [Key::Unknown]); // cut short
+    }
+
+    #[test]
+    fn escape_sequences() {
+        assert_eq!(keys(b"\x1b[A\x1b[B\x1b[C\x1b[D"), [Key::Up, Key::Down, Key::Right, Key::Left]);
+        assert_eq!(keys(b"\x1b[H\x1b[F\x1bOH\x1bOF"), [Key::Home, Key::End, Key::Home, Key::End]);
+        assert_eq!(keys(b"\x1b[1~\x1b[7~\x1b[4~\x1b[8~\x1b[3~"), [Key::Home, Key::Home, Key::End, Key::End, Key::Delete]);
+        // the whole sequence is read even when it isn't known, so nothing of it shows up as typed
+        assert_eq!(keys(b"\x1b[15~x"), [Key::Unknown, Key::Char('x')]);
+        assert_eq!(keys(b"\x1bx"), [Key::Unknown]);
+        assert_eq!(keys(b"\x1b[Z"), [Key::Unknown]);
+    }
+
+    #[test]
+    fn common_prefixes() {
+        assert_eq!(common_prefix("index-of", "insert"), "in");
+        assert_eq!(common_prefix("dup", "dup"), "dup");
+        assert_eq!(common_prefix("drop", "dr"), "dr");
+        assert_eq!(common_prefix("over", "swap"), "");
+        assert_eq!(common_prefix("éa", "éb"), "é");
+    }
+
+    #[test]
+    fn word_starts() {
+        assert_eq!(line("1 2 dup", 7).word_start(), 4);
+        assert_eq!(line("1 2 dup", 5).word_start(), 4);
+        assert_eq!(line("1 2 dup  ", 9).word_start(), 4); // spaces after the word go with it
+        assert_eq!(line("dup", 3).word_start(), 0);
+        assert_eq!(line("   ", 3).word_start(), 0);
+        assert_eq!(line("", 0).word_start(), 0);
+    }
+}
@@ -0,0 +1,157 @@
+use std::fmt;
+use crate::types::Type;
+
+// line and column of a token in the source, both start at 1, `len` is the width of the token in characters.
+// `source` says which piece of source code evaluated in the session the token comes from
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Span {
+    pub source: usize,
+    pub line: usize,
+    pub col: usize,
+    pub len: usize,
+}
+
+impl Span {
+    pub fn new(source: usize, line: usize, col: usize, len: usize) -> Span {
+        Span { source, line, col, len }
+    }
+}
+
+// extra information attached to an error, optionally pointing at another place in the source
+#[derive(Debug, Clone)]
+pub struct Note {
+    pub span: Option<Span>,
+    pub msg: String,
+}
+
+// one active word call at the time of a runtime error
+#[derive(Debug, Clone)]
+pub struct Frame {
+    pub word: String,
+    pub call_site: Span,
+}
+
+#[derive(Debug, Clone)]
+pub enum ForthError {
+    // bad characters, unclosed strings and lists. `eof` is set when the source simply ran out
+    // in the middle of a literal, so more input could still fix it
+    Lex { span: Span, msg: String, label: Option<String>, notes: Vec<Note>, eof: bool },
+    // unbalanced control flow, unknown words
+    Parse { span: Span, msg: String, label: Option<String>, notes: Vec<Note> },
+    // stack underflow, bad types, ... `span` and `word` point at the instruction that failed,
+    // `backtrace` lists the words that were being executed (innermost first) and
+    // `stack` is the data stack as it was when things went wrong. both are boxed slices, they are
+    // only ever read and it keeps every `Result` carrying an error small
+    Runtime {
+        span: Option<Span>,
+        word: Option<String>,
+        msg: String,
+        backtrace: Box<[Frame]>,
+        stack: Box<[Type]>,
+    },
+    Multiple(Vec<ForthError>), // everything the parser found wrong with a program, in source order
+}
+
+impl ForthError {
+    pub fn lex(span: Span, msg: impl Into<String>) -> ForthError {
+        ForthError::Lex { span, msg: msg.into(), label: None, notes: vec![], eof: false }
+    }
+
+    // the source ended before a string or list literal was closed
+    pub fn unexpected_eof(span: Span, msg: impl Into<String>) -> ForthError {
+        ForthError::Lex { span, msg: msg.into(), label: None, notes: vec![], eof: true }
+    }
+
+    pub fn is_eof(&self) -> bool {
+        matches!(self, ForthError::Lex { eof: true, .. })
+    }
+
+    pub fn parse(span: Span, msg: impl Into<String>) -> ForthError {
+        ForthError::Parse { span, msg: msg.into(), label: None, notes: vec![] }
+    }
+
+    // short text shown under the offending token
+    pub fn with_label(mut self, text: impl Into<String>) -> ForthError {
+        match &mut self {
+            ForthError::Lex { label, .. } | ForthError::Parse { label, .. } => *label = Some(text.into()),
+            _ => (),
+        }
+        self
+    }
+
+    pub fn with_note(mut self, span: Option<Span>, msg: impl Into<String>) -> ForthError {
+        match &mut self {
+            ForthError::Lex { notes, .. } | ForthError::Parse { notes, .. } => notes.push(Note { span, msg: msg.into() }),
+            _ => (),
+        }
+        self
+    }
+
+    // a single error is returned as is, more than one get wrapped up in `Multiple`
+    pub fn from_errors(mut errors: Vec<ForthError>) -> ForthError {
+        if errors.len() == 1 {
+            return errors.remove(0);
+        }
+        ForthError::Multiple(errors)
+    }
+
+    pub fn runtime(msg: impl Into<String>) -> ForthError {
+        ForthError::Runtime { span: None, word: None, msg: msg.into(), backtrace: Box::default(), stack: Box::default() }
+    }
+
+    // attach the location of the failing instruction to a runtime error, if it doesn't have one yet
+    pub fn at(self, location: Span, word: &str) -> ForthError {
+        match self {
+            ForthError::Runtime { span: None, msg, backtrace, stack, .. } => {
+                ForthError::Runtime { span: Some(location), word: Some(word.to_string()), msg, backtrace, stack }
+            }
+            e => e,
+        }
+    }
+
+    // attach the active word calls and the data stack to a runtime error
+    pub fn with_trace(self, frames: Vec<Frame>, data_stack: &[Type]) -> ForthError {
+        match self {
+            ForthError::Runtime { span, word, msg, .. } => {
+                ForthError::Runtime { span, word, msg, backtrace: frames.into_boxed_slice(), stack: data_stack.into() }
+            }
+            e => e,
+        }
+    }
+
+    pub fn span(&self) -> Option<Span> {
+        match self {
+            ForthError::Lex { span, .. } => Some(*span),
+            ForthError::Parse { span, .. } => Some(*span),
+            ForthError::Runtime { span, .. } => *span,
+            ForthError::Multiple(errors) => errors.first().and_then(|e| e.span()),
+        }
+    }
+
+    pub fn message(&self) -> &str {
+        match self {
+            ForthError::Lex { msg, .. } => msg,
+            ForthError::Parse { msg, .. } => msg,
+            ForthError::Runtime { msg, .. } => msg,
+            ForthError::Multiple(errors) => errors.first().map_or("", |e| e.message()),
+        }
+    }
+}
+
+impl fmt::Display for ForthError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let ForthError::Runtime { span: Some(span), word: Some(word), msg, .. } = self {
+            return write!(f, "{}:{} `{}`: {}", span.line, span.col, word, msg);
+        }
+        if let ForthError::Multiple(errors) = self {
+            let lines: Vec<String> = errors.iter().map(|e| e.to_string()).collect();
+            return write!(f, "{}", lines.join("\n"));
+        }
+        match self.span() {
+            Some(span) => write!(f, "{}:{} {}", span.line, span.col, self.message()),
+            None => write!(f, "{}", self.message()),
+        }
+    }
+}
+
+impl std::error::Error for ForthError {}
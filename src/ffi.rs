@@ -0,0 +1,214 @@
+// the C interface, declared in c/myforth.h. an interpreter is handed out as an opaque pointer,
+// every function returns 0 on success and -1 on failure, after which `myforth_last_error` says what went wrong.
+// a panic must never unwind into C, every entry point catches them and reports them as a failure
+
+use std::any::Any;
+use std::ffi::{c_char, c_double, c_int, CStr, CString};
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+use crate::*;
+
+pub struct MyForth {
+    interp: Interpreter,
+    error: Option<CString>, // the message behind the last -1
+}
+
+impl MyForth {
+    fn fail(&mut self, msg: String) -> c_int {
+        // a message with a nul byte in it would be cut short in C anyway
+        self.error = CString::new(msg.replace('\0', " ")).ok();
+        return -1;
+    }
+
+    fn done(&mut self, result: Result<(), ForthError>) -> c_int {
+        match result {
+            Ok(()) => return 0,
+            Err(e) => {
+                let msg = render(&e, self.interp.sources(), false);
+                return self.fail(msg);
+            }
+        }
+    }
+
+    // pop the top of the stack if `extract` takes it, otherwise it stays where it is
+    fn pop_with<T>(&mut self, expected: &str, extract: fn(&Type) -> Option<T>) -> Result<T, c_int> {
+        let value = match self.interp.stack().last() {
+            Some(top) => extract(top),
+            None => return Err(self.fail(format!("expected {} but the stack is empty", expected))),
+        };
+        match value {
+            Some(value) => {
+                self.interp.pop();
+                return Ok(value);
+            }
+            None => {
+                let top = self.interp.stack().last().map(|t| t.describe()).unwrap_or_default();
+                return Err(self.fail(format!("expected {} on top of the stack, got {}", expected, top)));
+            }
+        }
+    }
+}
+
+// SAFETY, for every function taking one: `forth` has to come from `myforth_new` and not be freed yet
+unsafe fn get<'a>(forth: *mut MyForth) -> &'a mut MyForth {
+    &mut *forth
+}
+
+// what a caught panic said
+fn panic_message(payload: Box<dyn Any + Send>) -> String {
+    let what = match payload.downcast::<String>() {
+        Ok(msg) => *msg,
+        Err(payload) => match payload.downcast::<&str>() {
+            Ok(msg) => String::from(*msg),
+            Err(_) => String::from("unknown cause"),
+        },
+    };
+    return format!("internal error, the interpreter panicked: {}", what);
+}
+
+// run the body of an entry point working on `forth`. the error of the call before is cleared first,
+// so it's only set again if this one fails. if it panics `failed` is returned instead
+// and the panic message is left for `myforth_last_error`
+unsafe fn guard<T>(forth: *mut MyForth, failed: T, body: impl FnOnce(&mut MyForth) -> T) -> T {
+    get(forth).error = None;
+    match panic::catch_unwind(AssertUnwindSafe(|| body(get(forth)))) {
+        Ok(value) => return value,
+        Err(payload) => {
+            get(forth).fail(panic_message(payload));
+            return failed;
+        }
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn myforth_new() -> *mut MyForth {
+    let new = || Box::into_raw(Box::new(MyForth { interp: Interpreter::new(), error: None }));
+    panic::catch_unwind(new).unwrap_or(ptr::null_mut())
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn myforth_free(forth: *mut MyForth) {
+    if !forth.is_null() {
+        // there is nobody left to tell about a panic here
+        let _ = panic::catch_unwind(AssertUnwindSafe(|| drop(Box::from_raw(forth))));
+    }
+}
+
+// `code` is a nul terminated UTF-8 string
+#[no_mangle]
+pub unsafe extern "C" fn myforth_eval(forth: *mut MyForth, code: *const c_char) -> c_int {
+    guard(forth, -1, |forth| match CStr::from_ptr(code).to_str() {
+        Ok(code) => {
+            let result = forth.interp.eval(code);
+            return forth.done(result);
+        }
+        Err(_) => return forth.fail(String::from("code is not valid UTF-8")),
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn myforth_push_int(forth: *mut MyForth, n: i64) -> c_int {
+    guard(forth, -1, |forth| {
+        forth.interp.push(Type::Number(Num::Integer(n)));
+        return 0;
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn myforth_push_float(forth: *mut MyForth, f: c_double) -> c_int {
+    guard(forth, -1, |forth| {
+        forth.interp.push(Type::Number(Num::Float(f)));
+        return 0;
+    })
+}
+
+// the string is copied
+#[no_mangle]
+pub unsafe extern "C" fn myforth_push_str(forth: *mut MyForth, s: *const c_char) -> c_int {
+    guard(forth, -1, |forth| match CStr::from_ptr(s).to_str() {
+        Ok(s) => {
+            forth.interp.push(Type::Str(String::from(s)));
+            return 0;
+        }
+        Err(_) => return forth.fail(String::from("string is not valid UTF-8")),
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn myforth_pop_int(forth: *mut MyForth, out: *mut i64) -> c_int {
+    guard(forth, -1, |forth| {
+        let value = forth.pop_with("an integer", |top| match top {
+            Type::Number(Num::Integer(n)) => Some(*n),
+            _ => None,
+        });
+        match value {
+            Ok(n) => {
+                *out = n;
+                return 0;
+            }
+            Err(code) => return code,
+        }
+    })
+}
+
+// integers are converted
+#[no_mangle]
+pub unsafe extern "C" fn myforth_pop_float(forth: *mut MyForth, out: *mut c_double) -> c_int {
+    guard(forth, -1, |forth| {
+        let value = forth.pop_with("a number", |top| match top {
+            Type::Number(Num::Float(f)) => Some(*f),
+            Type::Number(Num::Integer(n)) => Some(*n as f64),
+            _ => None,
+        });
+        match value {
+            Ok(f) => {
+                *out = f;
+                return 0;
+            }
+            Err(code) => return code,
+        }
+    })
+}
+
+// a new string that has to be given back with `myforth_free_str`, NULL if the top of the stack isn't a string
+#[no_mangle]
+pub unsafe extern "C" fn myforth_pop_str(forth: *mut MyForth) -> *mut c_char {
+    guard(forth, ptr::null_mut(), |forth| {
+        let value = forth.pop_with("a string", |top| match top {
+            Type::Str(s) => Some(s.replace('\0', " ")),
+            _ => None,
+        });
+        match value.map(CString::new) {
+            Ok(Ok(s)) => return s.into_raw(),
+            _ => return ptr::null_mut(),
+        }
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn myforth_free_str(s: *mut c_char) {
+    if !s.is_null() {
+        let _ = panic::catch_unwind(|| drop(CString::from_raw(s)));
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn myforth_depth(forth: *mut MyForth) -> usize {
+    guard(forth, 0, |forth| forth.interp.stack().len())
+}
+
+// NULL if the last call went fine, the message stays valid until the next call on `forth`
+#[no_mangle]
+pub unsafe extern "C" fn myforth_last_error(forth: *mut MyForth) -> *const c_char {
+    match &get(forth).error {
+        Some(msg) => msg.as_ptr(),
+        None => ptr::null(),
+    }
+}
+
+// only in debug builds, so c/test.c can check that a panic comes back as an error. not in the header
+#[cfg(debug_assertions)]
+#[no_mangle]
+pub unsafe extern "C" fn myforth_debug_panic(forth: *mut MyForth) -> c_int {
+    guard(forth, -1, |_| panic!("requested by myforth_debug_panic"))
+}
@@ -0,0 +1,475 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, BufRead, Write};
+use std::sync::{Arc, Mutex, MutexGuard};
+use crate::*;
+
+// an output sink that can be read back, to see what a program printed:
+//
+//     let output = OutputBuffer::new();
+//     interp.set_output(output.clone());
+//     interp.eval("\"hi\" out")?;
+//     output.contents() // "hi\n"
+#[derive(Debug, Clone, Default)]
+pub struct OutputBuffer(Arc<Mutex<Vec<u8>>>);
+
+impl OutputBuffer {
+    pub fn new() -> OutputBuffer {
+        OutputBuffer::default()
+    }
+
+    pub fn contents(&self) -> String {
+        String::from_utf8_lossy(&self.buffer()).into_owned()
+    }
+
+    // a write that panicked halfway can only have left some bytes behind, that is still worth reading
+    fn buffer(&self) -> MutexGuard<'_, Vec<u8>> {
+        self.0.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    pub fn clear(&self) {
+        self.buffer().clear();
+    }
+}
+
+impl Write for OutputBuffer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.buffer().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+// a session: words, variables and compiled code stick around between calls to `eval`,
+// which is what makes the repl usable for defining things one line at a time.
+// this is also what a program embedding the language works with. it is `Send`, so a session can be
+// handed to another thread, which is why registered words and the input and output have to be `Send` too
+pub struct Interpreter {
+    pub dump_tokens: bool,
+    pub dump_ops: bool,
+    pub trace: bool, // print every instruction before it runs
+    stack: Vec<Type>,
+    sources: Vec<Source>, // everything that was evaluated, spans point into this
+    program: Program,
+    mem: HashMap<String, Type>, // this is where the variables are stored
+    process: Process,
+}
+
+impl Default for Interpreter {
+    fn default() -> Interpreter {
+        Interpreter::new()
+    }
+}
+
+// the state of a session before something was evaluated, sources are never taken back
+struct Snapshot {
+    len: usize, // of the program
+    stack: Vec<Type>,
+    mem: HashMap<String, Type>,
+    words: HashMap<String, Word>,
+}
+
+impl Interpreter {
+    pub fn new() -> Interpreter {
+        Interpreter {
+            dump_tokens: false,
+            dump_ops: false,
+            trace: false,
+            stack: vec![],
+            sources: vec![],
+            program: Program::default(),
+            mem: HashMap::new(),
+            process: Process::default(),
+        }
+    }
+
+    // compile `text` on top of everything defined so far and run it.
+    // if anything goes wrong the session is put back the way it was before the call:
+    // the stack, the variables and the words all look like `text` was never evaluated
+    pub fn eval(&mut self, text: &str) -> Result<(), ForthError> {
+        self.eval_named("<eval>", text)
+    }
+
+    // `eval` where `name` is what the code is called in diagnostics, a file name for example
+    pub fn eval_named(&mut self, name: &str, text: &str) -> Result<(), ForthError> {
+        self.process.exit_code = None; // an `exit` only counts for the code that ran it
+        let id = self.add_source(name, text);
+        let start = self.program.ops.len();
+        let snapshot = self.snapshot();
+
+        let result = self.compile(text, id)
+                         .and_then(|_| run(&self.program, start, &mut self.stack, &mut self.mem, &mut self.process, self.trace));
+        let _ = self.process.output.flush(); // whatever was printed should show up now, even if it failed
+        if result.is_err() {
+            self.restore(snapshot); // the source stays, the error points into it
+        } else if !self.program.words.values().any(|word| word.start >= start) {
+            // nothing in there can be called again, so it doesn't have to stick around
+            self.program.ops.truncate(start);
+            self.program.locs.truncate(start);
+            self.sources.pop();
+        }
+        return result;
+    }
+
+    // compile `text` and check it without running anything, the session doesn't change
+    pub fn check(&mut self, name: &str, text: &str) -> Result<(), ForthError> {
+        let id = self.add_source(name, text);
+        let snapshot = self.snapshot();
+        let result = self.compile(text, id);
+        self.restore(snapshot);
+        if result.is_ok() {
+            self.sources.pop();
+        }
+        return result;
+    }
+
+    fn add_source(&mut self, name: &str, text: &str) -> usize {
+        self.sources.push(Source { name: String::from(name), text: String::from(text) });
+        return self.sources.len() - 1;
+    }
+
+    fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            len: self.program.ops.len(),
+            stack: self.stack.clone(),
+            mem: self.mem.clone(),
+            words: self.program.words.clone(), // a word can be redefined, so keep the old versions around
+        }
+    }
+
+    fn restore(&mut self, snapshot: Snapshot) {
+        self.program.ops.truncate(snapshot.len);
+        self.program.locs.truncate(snapshot.len);
+        self.program.words = snapshot.words;
+        self.stack = snapshot.stack;
+        self.mem = snapshot.mem;
+    }
+
+    // run the word `name` on the stack as it is, like `eval` the session is rolled back if it fails.
+    // the word is looked up the way code is compiled, a `defword` wins over a registered word
+    pub fn call_word(&mut self, name: &str) -> Result<(), ForthError> {
+        let call = if let Some(word) = self.program.words.get(name) {
+            Op::Call(word.start)
+        } else if let Some(n) = self.program.natives.iter().rposition(|native| native.name == name) {
+            Op::Native(n)
+        } else {
+            return Err(ForthError::runtime(format!("Unknown Word `{}`", name)));
+        };
+        self.process.exit_code = None;
+        let id = self.add_source("<call>", name);
+        let snapshot = self.snapshot();
+        let len = snapshot.len;
+
+        // a call that only exists for as long as it runs
+        let span = Span::new(id, 1, 1, name.chars().count());
+        self.program.ops.extend([call, Op::EndOfProgram]);
+        self.program.locs.extend([Loc { span, word: String::from(name) }, Loc { span, word: String::new() }]);
+        let result = run(&self.program, len, &mut self.stack, &mut self.mem, &mut self.process, self.trace);
+        let _ = self.process.output.flush();
+        if result.is_err() {
+            self.restore(snapshot); // the source stays, the error points into it
+        } else {
+            self.program.ops.truncate(len);
+            self.program.locs.truncate(len);
+            self.sources.pop();
+        }
+        return result;
+    }
+
+    pub fn push(&mut self, value: Type) {
+        self.stack.push(value);
+    }
+
+    pub fn pop(&mut self) -> Option<Type> {
+        self.stack.pop()
+    }
+
+    // bottom of the stack first
+    pub fn stack(&self) -> &[Type] {
+        &self.stack
+    }
+
+    pub fn clear_stack(&mut self) {
+        self.stack.clear();
+    }
+
+    pub fn get_var(&self, name: &str) -> Option<&Type> {
+        self.mem.get(name)
+    }
+
+    // the variable is declared if it doesn't exist yet
+    pub fn set_var(&mut self, name: &str, value: Type) {
+        self.mem.insert(String::from(name), value);
+    }
+
+    // send everything the interpreter prints somewhere other than stdout
+    pub fn set_output(&mut self, output: impl Write + Send + 'static) {
+        self.process.output = Box::new(output);
+    }
+
+    // where `readln` reads lines from instead of stdin
+    pub fn set_input(&mut self, input: impl BufRead + Send + 'static) {
+        self.process.input = Some(Box::new(input));
+    }
+
+    pub fn output(&mut self) -> &mut dyn Write {
+        self.process.output.as_mut()
+    }
+
+    // print the stack to the output, `debug` shows the values the way `{:?}` does
+    pub fn show_stack(&mut self, debug: bool) -> io::Result<()> {
+        let out = self.process.output.as_mut();
+        if debug {
+            show_stack_debug(out, &self.stack)?;
+        } else {
+            show_stack(out, &self.stack)?;
+        }
+        return out.flush();
+    }
+
+    // everything that was evaluated, for `render`
+    pub fn sources(&self) -> &[Source] {
+        &self.sources
+    }
+
+    // what `args` gives the program
+    pub fn set_args(&mut self, args: Vec<String>) {
+        self.process.args = args;
+    }
+
+    // the status the last `eval` or `call_word` asked to exit with through `exit` or `bye`, if it did
+    pub fn exit_code(&self) -> Option<i32> {
+        self.process.exit_code
+    }
+
+    // evaluate a whole file, diagnostics name it by its path
+    pub fn load_file(&mut self, path: &str) -> Result<(), ForthError> {
+        let text = fs::read_to_string(path)
+                      .map_err(|e| ForthError::runtime(format!("cannot read {}: {}", path, e)))?;
+        return self.eval_named(path, &text);
+    }
+
+    // forget every word and variable and empty the stack. the debug switches, the arguments,
+    // input and output and the registered words stay as they are
+    pub fn reset(&mut self) {
+        let process = Process { exit_code: None, ..std::mem::take(&mut self.process) };
+        let program = Program { natives: std::mem::take(&mut self.program.natives), ..Program::default() };
+        *self = Interpreter {
+            dump_tokens: self.dump_tokens,
+            dump_ops: self.dump_ops,
+            trace: self.trace,
+            program,
+            process,
+            ..Interpreter::new()
+        };
+    }
+
+    // make `func` available as the word `name` in everything compiled from now on. `effect` is what it does to
+    // the stack, the checker relies on it and running it checks it. words declared with `defword` and the
+    // built in ones win over registered words with the same name
+    pub fn register<F>(&mut self, name: &str, effect: Effect, func: F)
+    where
+        F: Fn(&mut Vec<Type>) -> Result<(), ForthError> + Send + 'static,
+    {
+        self.program.natives.push(Native { name: String::from(name), effect, func: Box::new(func) });
+    }
+
+    // the declared words sorted by name, with their signature if they have one
+    pub fn words(&self) -> Vec<(&str, Option<&Signature>)> {
+        let mut words: Vec<(&str, Option<&Signature>)> = self.program.words.iter()
+                                                             .map(|(name, w)| (name.as_str(), w.sig.as_ref()))
+                                                             .collect();
+        words.sort_by_key(|&(name, _)| name);
+        return words;
+    }
+
+    // the variables sorted by name
+    pub fn vars(&self) -> Vec<(&str, &Type)> {
+        let mut vars: Vec<(&str, &Type)> = self.mem.iter().map(|(name, v)| (name.as_str(), v)).collect();
+        vars.sort_by_key(|&(name, _)| name);
+        return vars;
+    }
+
+    // the definition of a word rebuilt from its compiled body, followed by the instructions themselves
+    pub fn see(&self, name: &str) -> Option<String> {
+        let word = self.program.words.get(name)?;
+        // the `Defword` right before the body jumps past the `return` at the end of it
+        let end = match self.program.ops[word.start - 1] {
+            Op::Defword(end) => end,
+            _ => return None,
+        };
+
+        let mut source = format!("defword {}", name);
+        if let Some(sig) = &word.sig {
+            source.push_str(&format!(" {}", sig));
+        }
+        let mut listing = String::new();
+        for address in word.start..end {
+            let op = &self.program.ops[address];
+            let text = match op {
+                Op::Defvar(var) => format!("defvar {}", var),
+                Op::PushStr(s) => format!("\"{}\"", s),
+                _ => self.program.locs[address].word.clone(),
+            };
+            source.push(' ');
+            source.push_str(&text);
+            listing.push_str(&format!("\n{:>6}: {:?}", address, op));
+        }
+        return Some(source + &listing);
+    }
+
+    fn compile(&mut self, text: &str, id: usize) -> Result<(), ForthError> {
+        let start = self.program.ops.len();
+        let tokens = tokenize(text, id)?;
+        // the dumps are only there to look at, failing to print them doesn't matter
+        if self.dump_tokens {
+            let _ = writeln!(self.process.output, "{:?}", tokens);
+        }
+        parse_to_program(&tokens, &mut self.program, id)?;
+        if self.dump_ops {
+            let _ = writeln!(self.process.output, "{:?}", &self.program.ops[start..]);
+        }
+        return check(&self.program, start, self.stack.len());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    #[test]
+    fn output_buffer_collects_what_was_printed() {
+        let mut interp = Interpreter::new();
+        let output = OutputBuffer::new();
+        interp.set_output(output.clone());
+        interp.eval("\"hi\" out 1 2 + out").unwrap();
+        assert_eq!(output.contents(), "hi\n3\n");
+        output.clear();
+        assert_eq!(output.contents(), "");
+        // what was printed before an error is still there
+        assert!(interp.eval("\"before\" out 1 0 /").is_err());
+        assert_eq!(output.contents(), "before\n");
+    }
+
+    #[test]
+    fn output_buffer_survives_a_poisoned_lock() {
+        let output = OutputBuffer::new();
+        let mut writer = output.clone();
+        writer.write_all(b"kept").unwrap();
+        let poisoner = output.clone();
+        let _ = thread::spawn(move || {
+            let _guard = poisoner.buffer();
+            panic!("poison the lock");
+        })
+        .join();
+        assert_eq!(output.contents(), "kept");
+        writer.write_all(b" and more").unwrap();
+        assert_eq!(output.contents(), "kept and more");
+    }
+
+    #[test]
+    fn a_session_can_move_to_another_thread() {
+        fn assert_send<T: Send>() {}
+        assert_send::<Interpreter>();
+
+        let mut interp = Interpreter::new();
+        let output = OutputBuffer::new();
+        interp.set_output(output.clone());
+        interp.register("sq", Effect { inputs: 1, outputs: 1 }, |stack| {
+            let n = pop_int(stack, "sq")?;
+            stack.push(Type::Number(Num::Integer(n * n)));
+            Ok(())
+        });
+        interp.eval("defword twice ( n -- n ) 2 * return").unwrap();
+        let mut interp = thread::spawn(move || {
+            interp.eval("3 sq twice out").unwrap();
+            interp
+        })
+        .join()
+        .unwrap();
+        interp.eval("1 twice out").unwrap();
+        assert_eq!(output.contents(), "18\n2\n");
+    }
+
+    #[test]
+    fn evals_that_declare_nothing_are_not_kept() {
+        let mut interp = Interpreter::new();
+        interp.set_output(OutputBuffer::new());
+        for _ in 0..100 {
+            interp.eval("1 2 + drop").unwrap();
+        }
+        assert_eq!(interp.program.ops.len(), 0);
+        assert_eq!(interp.sources().len(), 0);
+
+        // a word has to stay callable, and so does the source its errors point into
+        interp.eval("defword one ( -- n ) 1 return 2 drop").unwrap();
+        let len = interp.program.ops.len();
+        assert!(len > 0);
+        interp.eval("one drop").unwrap();
+        assert_eq!(interp.program.ops.len(), len);
+        assert_eq!(interp.sources().len(), 1);
+
+        // a failed eval keeps its source for the diagnostic but none of its code
+        assert!(interp.eval("one 0 /").is_err());
+        assert_eq!(interp.program.ops.len(), len);
+        assert_eq!(interp.sources().len(), 2);
+    }
+
+    #[test]
+    fn the_deepest_nesting_allowed_runs() {
+        let mut interp = Interpreter::new();
+        let output = OutputBuffer::new();
+        interp.set_output(output.clone());
+        let depth = 100;
+        let blocks = format!("{} {}", "1 if ".repeat(depth), "end ".repeat(depth));
+        let source = format!("{} {}{} len out", blocks, "[".repeat(depth), "]".repeat(depth));
+        interp.eval(&source).unwrap();
+        assert_eq!(output.contents(), "1\n");
+        let source = format!("{} {}", "1 if ".repeat(depth + 1), "end ".repeat(depth + 1));
+        assert_eq!(interp.eval(&source).unwrap_err().message(), "blocks and lists are nested too deeply");
+    }
+
+    #[test]
+    fn an_exit_only_counts_for_the_code_that_ran_it() {
+        let mut interp = Interpreter::new();
+        interp.set_output(OutputBuffer::new());
+        interp.eval("defword quit ( -- ) 4 exit return 3 exit").unwrap();
+        assert_eq!(interp.exit_code(), Some(3));
+        interp.eval("1 drop").unwrap();
+        assert_eq!(interp.exit_code(), None);
+        interp.call_word("quit").unwrap();
+        assert_eq!(interp.exit_code(), Some(4));
+        interp.eval("bye").unwrap();
+        assert_eq!(interp.exit_code(), Some(0));
+    }
+
+    #[test]
+    fn registered_words_can_be_called() {
+        let mut interp = Interpreter::new();
+        interp.register("sq", Effect { inputs: 1, outputs: 1 }, |stack| {
+            let n = pop_int(stack, "sq")?;
+            stack.push(Type::Number(Num::Integer(n * n)));
+            Ok(())
+        });
+        interp.push(Type::Number(Num::Integer(3)));
+        interp.call_word("sq").unwrap();
+        assert_eq!(interp.stack(), [Type::Number(Num::Integer(9))]);
+
+        // a failing call leaves the stack alone
+        interp.push(Type::Str(String::from("x")));
+        assert!(interp.call_word("sq").is_err());
+        assert_eq!(interp.stack().len(), 2);
+        interp.clear_stack();
+
+        // declared words come first, like when code is compiled
+        interp.eval("defword sq ( n -- n ) 1 + return").unwrap();
+        interp.push(Type::Number(Num::Integer(3)));
+        interp.call_word("sq").unwrap();
+        assert_eq!(interp.stack(), [Type::Number(Num::Integer(4))]);
+        assert_eq!(interp.call_word("nope").unwrap_err().message(), "Unknown Word `nope`");
+    }
+}
@@ -0,0 +1,244 @@
+// the lexer: turns source code into tokens in a single pass over its characters.
+// comments (`//` up to the end of the line) are skipped here too, except inside string literals
+
+use std::iter::Peekable;
+use std::str::CharIndices;
+use crate::error::*;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub(crate) enum TokenKind {
+    Num,
+    Bool,
+    Null,
+    Str,
+    Word,
+    VarOp, // an operation that acts on a variable
+    ListStart, // `[`, the parser puts the list literal together
+    ListEnd, // `]`
+}
+
+// `text` is the piece of source the token was read from, without the quotes for strings
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Token<'a> {
+    pub kind: TokenKind,
+    pub text: &'a str,
+    pub span: Span,
+}
+
+pub(crate) struct Lexer<'a> {
+    source: &'a str,
+    chars: Peekable<CharIndices<'a>>,
+    id: usize,
+    line: usize, // of the next character, counted in characters like everything in `Span`
+    col: usize,
+}
+
+impl<'a> Lexer<'a> {
+    // `id` identifies `source` in the session, see `Span`
+    pub fn new(source: &'a str, id: usize) -> Lexer<'a> {
+        Lexer { source, chars: source.char_indices().peekable(), id, line: 1, col: 1 }
+    }
+
+    // byte offset of the next character
+    fn offset(&mut self) -> usize {
+        self.chars.peek().map_or(self.source.len(), |&(i, _)| i)
+    }
+
+    fn peek(&mut self) -> Option<char> {
+        self.chars.peek().map(|&(_, c)| c)
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let (_, c) = self.chars.next()?;
+        if c == '\n' {
+            self.line += 1;
+            self.col = 1;
+        } else {
+            self.col += 1;
+        }
+        return Some(c);
+    }
+
+    fn at_comment(&mut self) -> bool {
+        let offset = self.offset();
+        self.source[offset..].starts_with("//")
+    }
+
+    fn skip_comment(&mut self) {
+        while self.peek().is_some_and(|c| c != '\n') {
+            self.bump();
+        }
+    }
+
+    fn here(&self, len: usize) -> Span {
+        Span::new(self.id, self.line, self.col, len)
+    }
+
+    // literals can't run straight into the next token, only into the `]` of a list they are in
+    fn separated(&mut self, start: Span, what: &str, label: &str) -> Result<(), ForthError> {
+        match self.peek() {
+            Some(c) if !c.is_whitespace() && c != ']' && !self.at_comment() => {
+                let msg = format!("Found unexpected character `{}` after {}", c, what);
+                return Err(ForthError::lex(start, msg).with_label(label));
+            }
+            _ => return Ok(()),
+        }
+    }
+
+    // from the opening quote to just past the closing one
+    fn skip_string(&mut self) -> Result<(), ForthError> {
+        let start = self.here(1);
+        self.bump();
+        loop {
+            match self.bump() {
+                Some('"') => return Ok(()),
+                Some(_) => (),
+                None => return Err(ForthError::unexpected_eof(start, "Unclosed String").with_label("string starts here")),
+            }
+        }
+    }
+
+    fn string(&mut self) -> Result<Token<'a>, ForthError> {
+        let start = self.here(1);
+        let from = self.offset() + 1; // the quotes are one byte each
+        self.skip_string()?;
+        let text = &self.source[from..self.offset()-1];
+        self.separated(start, "string literal", "string literals must be followed by whitespace")?;
+        return Ok(Token { kind: TokenKind::Str, text, span: Span { len: text.chars().count() + 2, ..start } });
+    }
+
+    // `[` or `]`
+    fn bracket(&mut self) -> Result<Token<'a>, ForthError> {
+        let span = self.here(1);
+        let from = self.offset();
+        let kind = match self.bump() {
+            Some('[') => TokenKind::ListStart,
+            _ => {
+                self.separated(span, "list", "list literals must be followed by whitespace")?;
+                TokenKind::ListEnd
+            }
+        };
+        return Ok(Token { kind, text: &self.source[from..from+1], span });
+    }
+
+    fn word(&mut self) -> Result<Token<'a>, ForthError> {
+        let start = self.here(0);
+        let from = self.offset();
+        while let Some(c) = self.peek() {
+            if c.is_whitespace() || c == ']' || self.at_comment() {
+                break;
+            }
+            if c == '"' || c == '[' {
+                let msg = format!("found unexpected {} in word", c);
+                return Err(ForthError::lex(self.here(1), msg).with_label("separate it with a space"));
+            }
+            self.bump();
+        }
+        let text = &self.source[from..self.offset()];
+        let kind = if text.parse::<i64>().is_ok() || text.parse::<f64>().is_ok() {
+            TokenKind::Num
+        } else if text == "true" || text == "false" {
+            TokenKind::Bool
+        } else if text == "null" {
+            TokenKind::Null
+        } else if text.starts_with('@') || text.starts_with('!') {
+            TokenKind::VarOp
+        } else {
+            TokenKind::Word
+        };
+        return Ok(Token { kind, text, span: Span { len: text.chars().count(), ..start } });
+    }
+}
+
+impl<'a> Iterator for Lexer<'a> {
+    type Item = Result<Token<'a>, ForthError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            match self.peek()? {
+                c if c.is_whitespace() => {
+                    self.bump();
+                }
+                _ if self.at_comment() => self.skip_comment(),
+                '"' => return Some(self.string()),
+                '[' | ']' => return Some(self.bracket()),
+                _ => return Some(self.word()),
+            }
+        }
+    }
+}
+
+// everything in `source`, or the first thing that couldn't be read
+pub(crate) fn tokenize(source: &str, id: usize) -> Result<Vec<Token<'_>>, ForthError> {
+    Lexer::new(source, id).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kinds(source: &str) -> Vec<(TokenKind, &str)> {
+        return tokenize(source, 0).unwrap().iter().map(|t| (t.kind, t.text)).collect();
+    }
+
+    #[test]
+    fn brackets_inside_strings_stay_in_the_string() {
+        assert_eq!(kinds("\"a]b[\" out"), [(TokenKind::Str, "a]b["), (TokenKind::Word, "out")]);
+        assert_eq!(
+            kinds("[\"]\" \"[\"]"),
+            [(TokenKind::ListStart, "["), (TokenKind::Str, "]"), (TokenKind::Str, "["), (TokenKind::ListEnd, "]")]
+        );
+        assert_eq!(kinds("\"// not a comment\""), [(TokenKind::Str, "// not a comment")]);
+    }
+
+    #[test]
+    fn lists_and_literals() {
+        assert_eq!(
+            kinds("[1 true null]] 2.5 !x @x // done"),
+            [
+                (TokenKind::ListStart, "["),
+                (TokenKind::Num, "1"),
+                (TokenKind::Bool, "true"),
+                (TokenKind::Null, "null"),
+                (TokenKind::ListEnd, "]"),
+                (TokenKind::ListEnd, "]"),
+                (TokenKind::Num, "2.5"),
+                (TokenKind::VarOp, "!x"),
+                (TokenKind::VarOp, "@x"),
+            ]
+        );
+    }
+
+    #[test]
+    fn spans_count_characters_not_bytes() {
+        let tokens = tokenize("\"héllo\" wörd\n  ∅ [\"ü\"]", 3).unwrap();
+        let spans: Vec<Span> = tokens.iter().map(|t| t.span).collect();
+        assert_eq!(
+            spans,
+            [
+                Span::new(3, 1, 1, 7),
+                Span::new(3, 1, 9, 4),
+                Span::new(3, 2, 3, 1),
+                Span::new(3, 2, 5, 1),
+                Span::new(3, 2, 6, 3),
+                Span::new(3, 2, 9, 1),
+            ]
+        );
+    }
+
+    #[test]
+    fn unclosed_string() {
+        let e = tokenize("1 \"abc", 0).unwrap_err();
+        assert!(e.is_eof());
+        assert_eq!(e.message(), "Unclosed String");
+        assert_eq!(e.span(), Some(Span::new(0, 1, 3, 1)));
+    }
+
+    #[test]
+    fn literals_need_a_separator() {
+        assert_eq!(tokenize("\"a\"b", 0).unwrap_err().message(), "Found unexpected character `b` after string literal");
+        assert_eq!(tokenize("[1]x", 0).unwrap_err().message(), "Found unexpected character `x` after list");
+        assert_eq!(tokenize("ab\"c\"", 0).unwrap_err().message(), "found unexpected \" in word");
+        assert!(tokenize("\"a\"] [1]// comment", 0).is_ok());
+    }
+}
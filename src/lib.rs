@@ -0,0 +1,750 @@
+// the language itself: lexer, parser, checker and the machine that runs the compiled code.
+// `Interpreter` is the way in, both for the `myforth` executable and for programs embedding the language:
+//
+//     let mut interp = Interpreter::new();
+//     interp.eval("defword sq dup * return")?;
+//     interp.push(Type::Number(Num::Integer(7)));
+//     interp.call_word("sq")?;
+//     let squared = interp.pop(); // Some(Number(Integer(49)))
+
+use std::collections::HashMap;
+use std::fmt;
+use std::io::{self, BufRead, Write};
+
+mod error;
+pub use error::*;
+mod diagnostic;
+pub use diagnostic::*;
+mod check;
+pub use check::*;
+mod interpreter;
+pub use interpreter::*;
+mod operations;
+pub use operations::*;
+mod types;
+pub use types::*;
+mod lexer;
+use lexer::*;
+mod parser;
+use parser::Node;
+mod ffi;
+
+#[derive(Debug, Clone)]
+enum Op {
+    PushInteger(i64),
+    PushFloat(f64),
+    PushStr(String),
+    PushBool(bool),
+    PushList(Vec<Type>),
+    PushNull,
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Floor,
+    Eq, // pop stack twice - push 1 or 0
+    Gt, // greater than
+    Lt, // less than
+    Gteq, // >= 
+    Lteq, // <=
+    Dup, // Duplicate value on the top of the stack
+    Swap, // swap 2 values on top of stack
+    Drop, // remove value on top of stack
+    Over, // copy element on bottom of the stack to the top of the stack 
+    Rotate, // rotate 3 values on top of the stack, a b c - b c a
+    Out, // pop stack - print to console
+    Index,
+    ListLen, // pop a list - push how many items it has
+    ListPush, // pop a value and a list - push the list with the value added to the end
+    ListPop, // pop a list - push it without its last item, then that item
+    ListSet, // pop a value, an index and a list - push the list with the item at the index replaced
+    ListInsert, // pop a value, an index and a list - push the list with the value inserted before the index
+    ListRemove, // pop an index and a list - push the list without the item at the index, then that item
+    Concat, // pop two lists - push them joined together
+    Slice, // pop two indexes and a list - push the items from the first index up to the second
+    Reverse,
+    Contains, // pop a value and a list - push whether the list holds the value
+    IndexOf, // pop a value and a list - push where the value first shows up in the list, or null
+    First,
+    Last,
+    ToList, // pop a count n - pop n values and push them as a list, the deepest one first
+    BeginList, // `{`, remember how deep the stack is
+    EndList(usize), // `}`, everything pushed since the `BeginList` at the label becomes a list
+    Readln, // push the next line of input, or null when there is none left
+    Args, // push the command line arguments of the program as a list of strings
+    Getenv, // pop a name - push the value of that environment variable, or null if it isn't set
+    Exit, // pop an exit status and stop the program
+    Bye, // stop the program with status 0
+    Defvar(String),
+    Readvar(String),
+    Writevar(String),
+    If(usize), // pop stack - if 0 jump to end, otherwise proceed
+    Ifstar(usize), // used in else-if blocks
+    Else(usize), // unconditional jump instruction
+    End(usize), // unconditional jump instruction
+    While, // just a label
+    Do(usize), // pop stack - if 0 jump to end, otherwise proceed, same as `if` but has different rules
+    Defword(usize), // unconditional jump
+    Return, // jump based on return stack
+    Call(usize), // jump to function definiton
+    Native(usize), // call a function registered by the host program, index into `Program::natives`
+    EndOfProgram,
+}
+
+// where an instruction came from, `word` is the token that produced it
+#[derive(Debug, Clone)]
+struct Loc {
+    span: Span,
+    word: String,
+}
+
+// a word declared with `defword`
+#[derive(Debug, Clone)]
+struct Word {
+    start: usize, // address of the body
+    sig: Option<Signature>, // the `( a b -- c )` stack effect, if one was declared
+    span: Span, // where the name was written in the declaration
+}
+
+// the Rust side of a word registered with `Interpreter::register`
+pub type NativeFn = dyn Fn(&mut Vec<Type>) -> Result<(), ForthError> + Send;
+
+// a word implemented by the program embedding the language
+struct Native {
+    name: String,
+    effect: Effect, // what the checker assumes it does, also checked when it runs
+    func: Box<NativeFn>,
+}
+
+impl fmt::Debug for Native {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Native({} ( {} -- {} ))", self.name, self.effect.inputs, self.effect.outputs)
+    }
+}
+
+// compiled code, `locs[i]` is the source location of `ops[i]`,
+// `words` holds everything declared with `defword`, `natives` everything registered from Rust.
+// new code gets compiled onto the end, so everything stays around for the whole session
+#[derive(Debug, Default)]
+struct Program {
+    ops: Vec<Op>,
+    locs: Vec<Loc>,
+    words: HashMap<String, Word>,
+    natives: Vec<Native>,
+}
+
+impl Program {
+    fn word_at(&self, address: usize) -> Option<&str> {
+        self.words.iter().find(|(_, word)| word.start == address).map(|(name, _)| name.as_str())
+    }
+
+    // rebuild the chain of active words from the return stack, innermost call first
+    fn backtrace(&self, return_stack: &[usize]) -> Vec<Frame> {
+        let mut frames = vec![];
+        for &ret in return_stack.iter().rev() {
+            let call = ret - 1; // return addresses point just past the `Call`
+            if let Op::Call(label) = self.ops[call] {
+                frames.push(Frame {
+                    word: self.word_at(label).unwrap_or("?").to_string(),
+                    call_site: self.locs[call].span,
+                });
+            }
+        }
+        return frames;
+    }
+}
+
+// what a running program gets to see of the process it runs in
+struct Process {
+    args: Vec<String>,
+    exit_code: Option<i32>, // set by `exit` and `bye`, the program stops right after
+    output: Box<dyn Write + Send>, // where `out` and everything else the interpreter prints goes
+    // where `readln` reads from, `None` is stdin. that one is read through `io::stdin()` every time
+    // instead of being wrapped up in a reader of its own so the repl doesn't lose any input to it
+    input: Option<Box<dyn BufRead + Send>>,
+}
+
+impl Default for Process {
+    fn default() -> Process {
+        Process { args: vec![], exit_code: None, output: Box::new(io::stdout()), input: None }
+    }
+}
+
+// a block or literal that is still open at the end of the input, the repl keeps reading lines until it's closed
+pub fn is_incomplete(input: &str) -> bool {
+    let tokens = match tokenize(input, 0) {
+        Ok(tokens) => tokens,
+        Err(e) => return e.is_eof(), // string literal still open
+    };
+
+    let mut depth: i64 = 0;
+    let mut in_signature = false;
+    for (k, token) in tokens.iter().enumerate() {
+        if in_signature {
+            in_signature = token.text != ")";
+            continue;
+        }
+        match (token.kind, token.text) {
+            (TokenKind::ListStart, _) => depth += 1,
+            (TokenKind::ListEnd, _) => depth -= 1,
+            (TokenKind::Word, "defword" | "if" | "while" | "{") => depth += 1,
+            (TokenKind::Word, "end" | "return" | "}") => depth -= 1,
+            // `defword name ( a -- b )`
+            (TokenKind::Word, "(") if k >= 2 && tokens[k-2].text == "defword" => in_signature = true,
+            _ => (),
+        }
+    }
+    // a name still has to follow
+    let waiting_for_name = matches!(tokens.last().map(|t| t.text), Some("defword") | Some("defvar"));
+    return depth > 0 || in_signature || waiting_for_name;
+}
+
+// only called on strings that are already known to parse as a number
+fn parse_to_num(n: &str) -> Num { 
+    match n.parse::<i64>() {
+        Ok(n) => Num::Integer(n),
+        Err(_) => Num::Float(n.parse::<f64>().unwrap_or(f64::NAN)),
+    }
+}
+
+// the value of a literal, the parser only lets literals into lists
+fn literal_value(node: &Node) -> Type {
+    match node {
+        Node::List { items, .. } => Type::List(items.iter().map(literal_value).collect()),
+        Node::Token(token) => match token.kind {
+            TokenKind::Num => Type::Number(parse_to_num(token.text)),
+            TokenKind::Str => Type::Str(String::from(token.text)),
+            TokenKind::Bool => Type::Boolean(token.text == "true"),
+            _ => Type::Null,
+        },
+        _ => Type::Null,
+    }
+}
+
+// a literal written out the way it would be in the source, for `Loc::word`
+fn literal_source(node: &Node) -> String {
+    match node {
+        Node::List { items, .. } => {
+            let items: Vec<String> = items.iter().map(literal_source).collect();
+            format!("[{}]", items.join(" "))
+        }
+        Node::Token(token) if token.kind == TokenKind::Str => format!("\"{}\"", token.text),
+        Node::Token(token) => String::from(token.text),
+        _ => String::new(),
+    }
+}
+
+// the built in words that compile to a single instruction, `builtin_op` looks words up in here
+const BUILTIN_OPS: &[(&str, Op)] = &[
+    ("+", Op::Add), ("-", Op::Sub), ("*", Op::Mul), ("/", Op::Div), ("=", Op::Eq), (">", Op::Gt), ("<", Op::Lt),
+    (">=", Op::Gteq), ("<=", Op::Lteq), ("out", Op::Out), ("dup", Op::Dup), ("swap", Op::Swap),
+    ("drop", Op::Drop), ("over", Op::Over), ("rotate", Op::Rotate), ("floor", Op::Floor), ("idx", Op::Index),
+    ("readln", Op::Readln), ("args", Op::Args), ("getenv", Op::Getenv), ("exit", Op::Exit), ("bye", Op::Bye),
+    (">list", Op::ToList), ("len", Op::ListLen), ("push", Op::ListPush), ("pop", Op::ListPop),
+    ("set", Op::ListSet), ("insert", Op::ListInsert), ("remove", Op::ListRemove), ("concat", Op::Concat),
+    ("slice", Op::Slice), ("reverse", Op::Reverse), ("contains", Op::Contains), ("index-of", Op::IndexOf),
+    ("first", Op::First), ("last", Op::Last),
+];
+
+// the words the parser takes care of itself, and the literals
+const KEYWORDS: &[&str] = &[
+    "{", "}", "defvar", "if", "if*", "else", "end", "while", "do", "defword", "return", "true", "false", "null",
+];
+
+// every word `parse_to_program` knows about without it being declared
+pub fn builtins() -> impl Iterator<Item = &'static str> {
+    return BUILTIN_OPS.iter().map(|(name, _)| *name).chain(KEYWORDS.iter().copied());
+}
+
+// the instruction a built in word compiles to
+fn builtin_op(word: &str) -> Option<Op> {
+    return BUILTIN_OPS.iter().find(|(name, _)| *name == word).map(|(_, op)| op.clone());
+}
+
+// compiles onto the end of `out`, words that are already in there can be called.
+// `id` identifies the source the tokens came from, see `Span`.
+// on error `out` is left half-written, the caller is expected to roll it back
+fn parse_to_program(tokens: &[Token], out: &mut Program, id: usize) -> Result<(), ForthError> {
+    let start = out.ops.len();
+    let (nodes, mut errors) = parser::parse(tokens);
+    // even when the structure is broken, compiling what's there still turns up the unknown words
+    compile_nodes(nodes, out, &mut errors);
+
+    if !errors.is_empty() {
+        errors.sort_by_key(|e| e.span().map(|s| (s.line, s.col)));
+        return Err(ForthError::from_errors(errors));
+    }
+
+    let end = match out.locs.last() {
+        Some(loc) if out.locs.len() > start => loc.span,
+        _ => Span::new(id, 1, 1, 0),
+    };
+    out.ops.push(Op::EndOfProgram);
+    out.locs.push(Loc { span: end, word: String::new() });
+    return Ok(());
+}
+
+// the address `op` ended up at
+fn emit(out: &mut Program, op: Op, token: &Token) -> usize {
+    out.ops.push(op);
+    out.locs.push(Loc { span: token.span, word: String::from(token.text) });
+    return out.ops.len() - 1;
+}
+
+// point the jump at `address` to `to`
+fn set_label(out: &mut Program, address: usize, to: usize) {
+    match &mut out.ops[address] {
+        Op::If(n) | Op::Ifstar(n) | Op::Else(n) | Op::Do(n) | Op::Defword(n) => *n = to,
+        _ => (),
+    }
+}
+
+fn compile_nodes(nodes: Vec<Node>, out: &mut Program, errors: &mut Vec<ForthError>) {
+    for node in nodes {
+        match node {
+            Node::Token(token) => compile_token(&token, out, errors),
+            Node::List { open, ref items, ref close } => {
+                // the span covers the whole list when it's written on one line
+                let len = match close {
+                    Some(close) if close.span.line == open.span.line => close.span.col + 1 - open.span.col,
+                    _ => 1,
+                };
+                out.ops.push(Op::PushList(items.iter().map(literal_value).collect()));
+                out.locs.push(Loc { span: Span { len, ..open.span }, word: literal_source(&node) });
+            }
+            Node::Build { open, body, close } => {
+                let start = emit(out, Op::BeginList, &open);
+                compile_nodes(body, out, errors);
+                if let Some(close) = close {
+                    emit(out, Op::EndList(start), &close);
+                }
+            }
+            Node::Defvar { keyword, name } => {
+                emit(out, Op::Defvar(String::from(name.text)), &keyword);
+            }
+            Node::If { keyword, body, elses, end } => {
+                // an `if` or `if*` jumps just past the `else` that follows it, or to the `end`.
+                // every `else` jumps to the next `else` in the chain, the last one to the `end`
+                let mut open = Some(emit(out, Op::If(0), &keyword));
+                compile_nodes(body, out, errors);
+                let mut last_else = None;
+                for branch in elses {
+                    let at = emit(out, Op::Else(0), &branch.keyword);
+                    if let Some(open) = open {
+                        set_label(out, open, at+1);
+                    }
+                    if let Some(last_else) = last_else {
+                        set_label(out, last_else, at);
+                    }
+                    last_else = Some(at);
+                    compile_nodes(branch.body, out, errors);
+                    open = match branch.branch {
+                        Some((ifstar, body)) => {
+                            let at = emit(out, Op::Ifstar(0), &ifstar);
+                            compile_nodes(body, out, errors);
+                            Some(at)
+                        }
+                        None => None,
+                    };
+                }
+                let at = out.ops.len();
+                for jump in open.into_iter().chain(last_else) {
+                    set_label(out, jump, at);
+                }
+                if let Some(end) = end {
+                    emit(out, Op::End(at+1), &end);
+                }
+            }
+            Node::While { keyword, condition, body, end } => {
+                let start = emit(out, Op::While, &keyword);
+                compile_nodes(condition, out, errors);
+                if let Some((do_keyword, body)) = body {
+                    let check = emit(out, Op::Do(0), &do_keyword);
+                    compile_nodes(body, out, errors);
+                    if let Some(end) = end {
+                        let at = emit(out, Op::End(start), &end);
+                        set_label(out, check, at+1);
+                    }
+                }
+            }
+            Node::Defword { keyword, name, sig, body, end } => {
+                let at = emit(out, Op::Defword(0), &keyword);
+                // declared before the body so the word can call itself
+                out.words.insert(String::from(name.text), Word { start: at+1, sig, span: name.span });
+                compile_nodes(body, out, errors);
+                if let Some(end) = end {
+                    let ret = emit(out, Op::Return, &end);
+                    set_label(out, at, ret+1);
+                }
+            }
+        }
+    }
+}
+
+fn compile_token(token: &Token, out: &mut Program, errors: &mut Vec<ForthError>) {
+    let literal = token.text;
+    let op = match token.kind {
+        TokenKind::Word => {
+            // built in words come first, then words declared in forth, a later registration of the same name wins
+            if let Some(op) = builtin_op(literal) {
+                op
+            } else if let Some(w) = out.words.get(literal) {
+                Op::Call(w.start)
+            } else if let Some(n) = out.natives.iter().rposition(|native| native.name == literal) {
+                Op::Native(n)
+            } else {
+                let e = ForthError::parse(token.span, format!("Unknown Word `{}` Encountered", literal))
+                    .with_label("not defined")
+                    .with_note(None, "words have to be declared with `defword` before they are used");
+                errors.push(e);
+                return;
+            }
+        }
+        // the lexer only hands us literals that already parse as numbers
+        TokenKind::Num => match parse_to_num(literal) {
+            Num::Integer(n) => Op::PushInteger(n),
+            Num::Float(f) => Op::PushFloat(f),
+        },
+        TokenKind::Bool => Op::PushBool(literal == "true"),
+        TokenKind::Null => Op::PushNull,
+        TokenKind::Str => Op::PushStr(String::from(literal)),
+        TokenKind::VarOp => {
+            let var_name = String::from(&literal[1..]);
+            if literal.starts_with('@') {
+                Op::Writevar(var_name)
+            } else {
+                Op::Readvar(var_name)
+            }
+        }
+        // lists are put together by the parser
+        TokenKind::ListStart | TokenKind::ListEnd => return,
+    };
+    emit(out, op, token);
+}
+
+// run `program` from `start` until it hits `EndOfProgram`, `mem` is where the variables are stored.
+// with `trace` every instruction is printed to stderr along with the stack it is about to work on
+fn run(program: &Program, start: usize, s: &mut Vec<Type>, mem: &mut HashMap<String, Type>,
+       process: &mut Process, trace: bool) -> Result<(), ForthError> {
+    // `ip` stands for `instruction pointer`
+    let mut return_stack: Vec<usize> = vec![];
+    let mut marks: Vec<usize> = vec![]; // stack depths at the `{`s of the lists being built
+    let mut ip = start;
+    while ip < program.ops.len() {
+        let at = ip;
+        if trace {
+            eprintln!("{:>5}: {:<24} {}", ip, format!("{:?}", program.ops[ip]), format_stack(s));
+        }
+        if let Err(e) = step(&program.ops, &program.natives, &mut ip, s, mem, &mut return_stack, &mut marks, process) {
+            let loc = &program.locs[at];
+            return Err(e.at(loc.span, &loc.word).with_trace(program.backtrace(&return_stack), s));
+        }
+    }
+    return Ok(());
+}
+
+// execute the instruction at `ip` and move `ip` to the next one
+#[allow(clippy::too_many_arguments)] // the machine state, taken apart so `run` can still look at the program
+fn step(program: &[Op], natives: &[Native], ip: &mut usize, s: &mut Vec<Type>, mem: &mut HashMap<String, Type>,
+        return_stack: &mut Vec<usize>, marks: &mut Vec<usize>, process: &mut Process) -> Result<(), ForthError> {
+    match program[*ip] {
+        Op::PushInteger(n) => {
+            s.push(Type::Number(Num::Integer(n)));
+            *ip+=1;
+        }
+        Op::PushFloat(f) => {
+            s.push(Type::Number(Num::Float(f)));
+            *ip+=1;
+        }
+        Op::PushBool(b) => {
+            s.push(Type::Boolean(b));
+            *ip+=1;
+        }
+        Op::PushStr(ref string) => {
+            s.push(Type::Str(string.clone()));
+            *ip+=1;
+        }
+        Op::PushList(ref list) => {
+            s.push(Type::List(list.clone()));
+            *ip+=1;
+        }
+        Op::PushNull => {
+            s.push(Type::Null);
+            *ip+=1;
+        }
+        Op::Add => {
+            OP_ADD(s)?;
+            *ip+=1;
+        }
+        Op::Sub => {
+            OP_SUB(s)?;
+            *ip+=1;
+        }
+        Op::Mul => {
+            OP_MUL(s)?;
+            *ip+=1;
+        }
+        Op::Div => {
+            OP_DIV(s)?;
+            *ip+=1;
+        }
+        Op::Eq => {
+            OP_EQ(s)?;
+            *ip+=1;
+        }
+        Op::Gt => {
+            OP_GT(s)?;
+            *ip+=1;
+        }
+        Op::Lt => {
+            OP_LT(s)?;
+            *ip+=1;
+        }
+        Op::Gteq => {
+            OP_GTEQ(s)?;
+            *ip+=1;
+        }
+        Op::Lteq => {
+            OP_LTEQ(s)?;
+            *ip+=1;
+        }
+        Op::Out => {
+            OP_OUT(s, process.output.as_mut())?;
+            *ip+=1;
+        }
+        Op::Dup => {
+            OP_DUP(s)?;
+            *ip+=1;
+        }
+        Op::Swap => {
+            OP_SWAP(s)?;
+            *ip+=1;
+        }
+        Op::Drop => {
+            OP_DROP(s)?;
+            *ip+=1;
+        }
+        Op::Over => {
+            OP_OVER(s)?;
+            *ip+=1;
+        }
+        Op::Rotate => {
+            OP_ROTATE(s)?;
+            *ip+=1;
+        }
+        Op::Floor => {
+            OP_FLOOR(s)?;
+            *ip+=1;
+        }
+        Op::Index => {
+            OP_INDEX(s)?;
+            *ip+=1;
+        }
+        Op::ListLen => {
+            OP_LEN(s)?;
+            *ip+=1;
+        }
+        Op::ListPush => {
+            OP_LIST_PUSH(s)?;
+            *ip+=1;
+        }
+        Op::ListPop => {
+            OP_LIST_POP(s)?;
+            *ip+=1;
+        }
+        Op::ListSet => {
+            OP_LIST_SET(s)?;
+            *ip+=1;
+        }
+        Op::ListInsert => {
+            OP_LIST_INSERT(s)?;
+            *ip+=1;
+        }
+        Op::ListRemove => {
+            OP_LIST_REMOVE(s)?;
+            *ip+=1;
+        }
+        Op::Concat => {
+            OP_CONCAT(s)?;
+            *ip+=1;
+        }
+        Op::Slice => {
+            OP_SLICE(s)?;
+            *ip+=1;
+        }
+        Op::Reverse => {
+            OP_REVERSE(s)?;
+            *ip+=1;
+        }
+        Op::Contains => {
+            OP_CONTAINS(s)?;
+            *ip+=1;
+        }
+        Op::IndexOf => {
+            OP_INDEX_OF(s)?;
+            *ip+=1;
+        }
+        Op::First => {
+            OP_FIRST(s)?;
+            *ip+=1;
+        }
+        Op::Last => {
+            OP_LAST(s)?;
+            *ip+=1;
+        }
+        Op::ToList => {
+            OP_TO_LIST(s)?;
+            *ip+=1;
+        }
+        Op::BeginList => {
+            marks.push(s.len());
+            *ip+=1;
+        }
+        Op::EndList(_) => {
+            // every `EndList` has its `BeginList` right before it in the same block
+            let mark = marks.pop().unwrap_or(0);
+            if s.len() < mark {
+                return Err(ForthError::runtime(format!("the list took {} value(s) from before its `{{`", mark - s.len())));
+            }
+            let items = s.split_off(mark);
+            s.push(Type::List(items));
+            *ip+=1;
+        }
+        Op::Readln => {
+            match &mut process.input {
+                Some(input) => OP_READLN(s, input.as_mut())?,
+                None => OP_READLN(s, &mut io::stdin().lock())?,
+            }
+            *ip+=1;
+        }
+        Op::Args => {
+            s.push(Type::List(process.args.iter().map(|arg| Type::Str(arg.clone())).collect()));
+            *ip+=1;
+        }
+        Op::Getenv => {
+            OP_GETENV(s)?;
+            *ip+=1;
+        }
+        Op::Exit => {
+            let code = pop_int(s, "exit")?;
+            if !(0..=255).contains(&code) {
+                s.push(Type::Number(Num::Integer(code)));
+                return Err(ForthError::runtime(format!("`exit` status {} is out of range, expected 0 to 255", code)));
+            }
+            process.exit_code = Some(code as i32);
+            *ip = program.len();
+        }
+        Op::Bye => {
+            process.exit_code = Some(0);
+            *ip = program.len();
+        }
+        Op::Defvar(ref var_name) => {
+            mem.insert(var_name.clone(), Type::Null);
+            *ip+=1;
+        }
+        Op::Writevar(ref var_name) => {
+            match mem.get_mut(var_name) {
+                Some(var) => *var = pop_any(s, &format!("@{}", var_name))?,
+                _ => return Err(ForthError::runtime(format!("Variable {} has not beed initialized", var_name))),
+            };
+            *ip+=1;
+        }
+        Op::Readvar(ref var_name) => {
+            if let Some(val) = mem.get(var_name) {
+                s.push(val.clone());
+            } else {
+                return Err(ForthError::runtime(format!("Variable {} has not been initialized", var_name)));
+            }
+            *ip+=1;
+        }
+        Op::If(label) => {
+            let x = pop_any(s, "if")?;
+            if is_falsy(x) {
+                *ip = label;
+            } else {
+                *ip+=1;
+            }
+        }
+        Op::Ifstar(label) => {
+            let x = pop_any(s, "if*")?;
+            if is_falsy(x) {
+                *ip = label;
+            } else {
+                *ip+=1;
+            }
+        }
+        Op::Else(label) => *ip = label,
+        Op::End(label) => *ip = label,
+        Op::While => *ip+=1, // doesnt do anything, just a label to jump to
+        Op::Do(label) => {
+            let x = pop_any(s, "do")?;
+            if is_falsy(x) {
+                *ip = label;
+            } else {
+                *ip+=1;
+            }
+        }
+        Op::Defword(label) => *ip = label,
+        Op::Return => {
+            // parse_to_program only allows `return` at the end of a word, so this means we fell into a word body
+            *ip = return_stack.pop().ok_or_else(|| ForthError::runtime("`return` outside of a word call"))?;
+        }
+        Op::Call(label) => {
+            return_stack.push(*ip+1);
+            *ip = label;
+        }
+        Op::Native(n) => {
+            let native = &natives[n];
+            let depth = s.len();
+            (native.func)(s)?;
+            // the checker took the declared effect at its word, so it has to be right
+            if s.len() + native.effect.inputs != depth + native.effect.outputs {
+                let msg = format!("`{}` is registered as ( {} -- {} ) but went from {} to {} value(s) on the stack",
+                                  native.name, native.effect.inputs, native.effect.outputs, depth, s.len());
+                return Err(ForthError::runtime(msg));
+            }
+            *ip+=1;
+        }
+        Op::EndOfProgram => *ip = program.len(),
+    }
+    return Ok(());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn open_blocks_are_incomplete() {
+        assert!(is_incomplete("defword sq dup *"));
+        assert!(is_incomplete("1 if 2"));
+        assert!(is_incomplete("1 if 2 else 3"));
+        assert!(is_incomplete("while 1 do"));
+        assert!(is_incomplete("{ 1 2"));
+        assert!(is_incomplete("[1 [2]"));
+        assert!(is_incomplete("defword w 1 if 2 end"));
+    }
+
+    #[test]
+    fn open_literals_and_names_are_incomplete() {
+        assert!(is_incomplete("\"abc"));
+        assert!(is_incomplete("1 \"a\nb"));
+        assert!(is_incomplete("defword sq ( n --"));
+        assert!(is_incomplete("defvar"));
+        assert!(is_incomplete("1 out defword"));
+    }
+
+    #[test]
+    fn closed_input_is_complete() {
+        assert!(!is_incomplete(""));
+        assert!(!is_incomplete("1 2 + out"));
+        assert!(!is_incomplete("defword sq ( n -- n ) dup * return"));
+        assert!(!is_incomplete("1 if 2 else 3 if* 4 else 5 end while 0 do end"));
+        assert!(!is_incomplete("{ 1 [2 \"]\"] } drop"));
+        assert!(!is_incomplete("defvar x \"if\" // while"));
+        // too many closers is a mistake for the parser to report, there is nothing left to wait for
+        assert!(!is_incomplete("end"));
+        // and so are characters the lexer can't make sense of
+        assert!(!is_incomplete("\"a\"b if"));
+    }
+}
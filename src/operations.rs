@@ -1,148 +1,636 @@
 #![allow(non_snake_case)]
 
 use crate::types::*;
+use crate::error::ForthError;
+use std::env;
+use std::io::{BufRead, Write};
 
-pub fn show_stack(stack: &Vec<Type>) {
+pub fn format_stack(stack: &[Type]) -> String {
     let mut output = String::from("[ ");
     for v in stack {
         let val = format!("{} ", v);
         output.push_str(&val);
     }
     output.push(']');
-    println!("STACK TRACE: {}", output);
+    return output;
 }
 
-pub fn show_stack_debug(stack: &Vec<Type>) {
+pub fn show_stack(out: &mut dyn Write, stack: &[Type]) -> std::io::Result<()> {
+    writeln!(out, "STACK TRACE: {}", format_stack(stack))
+}
+
+pub fn show_stack_debug(out: &mut dyn Write, stack: &[Type]) -> std::io::Result<()> {
     let mut output = String::from("[ ");
     for v in stack {
         let val = format!("{:?} ", v);
         output.push_str(&val);
     }
     output.push(']');
-    println!("STACK TRACE: {}", output);
+    writeln!(out, "STACK TRACE: {}", output)
+}
+
+// what an operation needs to find at some position on the stack
+#[derive(Clone, Copy)]
+enum Operand {
+    Any,
+    Number,
+    Int,
+    Str,
+    List,
+}
+
+impl Operand {
+    fn fits(self, value: &Type) -> bool {
+        matches!((self, value),
+                 (Operand::Any, _)
+                 | (Operand::Number, Type::Number(_))
+                 | (Operand::Int, Type::Number(Num::Integer(_)))
+                 | (Operand::Str, Type::Str(_))
+                 | (Operand::List, Type::List(_)))
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Operand::Any => "a value",
+            Operand::Number => "Number",
+            Operand::Int => "Number (integer)",
+            Operand::Str => "Str",
+            Operand::List => "List",
+        }
+    }
+}
+
+// make sure the values `op` works on are there and of the right type, top of the stack first,
+// before any of them are taken off. every operation goes through this before it pops anything,
+// so one that fails leaves the stack as it was and the error report shows what it failed on
+fn check_operands(stack: &[Type], op: &str, operands: &[Operand]) -> Result<(), ForthError> {
+    for (k, operand) in operands.iter().enumerate() {
+        let n = match stack.len().checked_sub(k+1) {
+            Some(n) => n,
+            None => {
+                let msg = format!("stack underflow, `{}` needs {} value(s) but the stack has {}", op, operands.len(), stack.len());
+                return Err(ForthError::runtime(msg));
+            }
+        };
+        if !operand.fits(&stack[n]) {
+            let msg = format!("`{}` expected {}, got {} (value {} from the top, stack depth {})",
+                              op, operand.name(), stack[n].describe(), k+1, stack.len());
+            return Err(ForthError::runtime(msg));
+        }
+    }
+    Ok(())
 }
 
-fn destructure_number(n: Type) -> Num {
-    match n {
-        Type::Number(n) => return n,
-        _ => panic!("Expected number"),
-        
+// the top of the stack once `check_operands` said it fits, turned into what the operation works with
+fn take<T>(stack: &mut Vec<Type>, extract: fn(Type) -> Option<T>) -> T {
+    stack.pop().and_then(extract).expect("operands are checked before they are taken")
+}
+
+fn as_num(v: Type) -> Option<Num> {
+    match v {
+        Type::Number(n) => Some(n),
+        _ => None,
+    }
+}
+
+fn as_int(v: Type) -> Option<i64> {
+    match v {
+        Type::Number(Num::Integer(n)) => Some(n),
+        _ => None,
+    }
+}
+
+fn as_list(v: Type) -> Option<Vec<Type>> {
+    match v {
+        Type::List(l) => Some(l),
+        _ => None,
+    }
+}
+
+fn as_str(v: Type) -> Option<String> {
+    match v {
+        Type::Str(s) => Some(s),
+        _ => None,
     }
 }
-macro_rules! pop {
-    ($stack:expr) => {
-        $stack.pop().expect("stack underflow").clone()
-    };
+
+// the top of the stack for the operation `op`, for the ones that only take a single value
+// and for registered words
+pub fn pop_any(stack: &mut Vec<Type>, op: &str) -> Result<Type, ForthError> {
+    check_operands(stack, op, &[Operand::Any])?;
+    Ok(take(stack, Some))
+}
+
+pub fn pop_num(stack: &mut Vec<Type>, op: &str) -> Result<Num, ForthError> {
+    check_operands(stack, op, &[Operand::Number])?;
+    Ok(take(stack, as_num))
+}
+
+// both operands of a binary operation on numbers, (second from the top, top)
+pub fn pop_two_nums(stack: &mut Vec<Type>, op: &str) -> Result<(Num, Num), ForthError> {
+    check_operands(stack, op, &[Operand::Number, Operand::Number])?;
+    let x = take(stack, as_num);
+    let y = take(stack, as_num);
+    return Ok((y, x));
 }
-macro_rules! pop_num {
-    ($stack:expr) => {
-        destructure_number($stack.pop().expect("stack underflow"))
-    };
+
+pub fn pop_int(stack: &mut Vec<Type>, op: &str) -> Result<i64, ForthError> {
+    check_operands(stack, op, &[Operand::Int])?;
+    Ok(take(stack, as_int))
 }
 
-pub fn OP_ADD(stack: &mut Vec<Type>) {
-    let x = pop_num!(stack);
-    let y = pop_num!(stack);
+pub fn pop_list(stack: &mut Vec<Type>, op: &str) -> Result<Vec<Type>, ForthError> {
+    check_operands(stack, op, &[Operand::List])?;
+    Ok(take(stack, as_list))
+}
 
-    stack.push(Type::Number(y + x))
+pub fn pop_str(stack: &mut Vec<Type>, op: &str) -> Result<String, ForthError> {
+    check_operands(stack, op, &[Operand::Str])?;
+    Ok(take(stack, as_str))
+}
+
+// `i` as a position in a list of `len` items, negative ones count back from the end.
+// `past_end` allows the position right after the last item, where something can still be inserted
+fn list_index(i: i64, len: usize, past_end: bool) -> Option<usize> {
+    let len = len as i64;
+    let i = if i < 0 { i + len } else { i };
+    let last = if past_end { len } else { len - 1 };
+    if i >= 0 && i <= last {
+        return Some(i as usize);
+    }
+    return None;
 }
-pub fn OP_SUB(stack: &mut Vec<Type>) {
-    let x = pop_num!(stack);
-    let y = pop_num!(stack);
 
-    stack.push(Type::Number(y - x))
+fn out_of_range(op: &str, i: i64, len: usize) -> ForthError {
+    ForthError::runtime(format!("`{}` index {} out of range for list of length {}", op, i, len))
 }
-pub fn OP_MUL(stack: &mut Vec<Type>) {
-    let x = pop_num!(stack);
-    let y = pop_num!(stack);
 
-    stack.push(Type::Number(y * x))
+// the result of arithmetic that can fail on integers, when it did the operands go back on the stack
+fn push_checked(stack: &mut Vec<Type>, op: &str, y: Num, x: Num, result: Option<Num>) -> Result<(), ForthError> {
+    match result {
+        Some(n) => {
+            stack.push(Type::Number(n));
+            Ok(())
+        }
+        None => {
+            let msg = match (op, x) {
+                ("/", Num::Integer(0)) => String::from("division by zero"),
+                _ => format!("integer overflow in `{}` with {} and {}", op, y, x),
+            };
+            stack.extend([Type::Number(y), Type::Number(x)]);
+            Err(ForthError::runtime(msg))
+        }
+    }
 }
-pub fn OP_DIV(stack: &mut Vec<Type>) {
-    let x = pop_num!(stack);
-    let y = pop_num!(stack);
 
-    stack.push(Type::Number(y / x))
+pub fn OP_ADD(stack: &mut Vec<Type>) -> Result<(), ForthError> {
+    let (y, x) = pop_two_nums(stack, "+")?;
+    push_checked(stack, "+", y, x, y.checked_add(x))
+}
+pub fn OP_SUB(stack: &mut Vec<Type>) -> Result<(), ForthError> {
+    let (y, x) = pop_two_nums(stack, "-")?;
+    push_checked(stack, "-", y, x, y.checked_sub(x))
 }
-pub fn OP_FLOOR(stack: &mut Vec<Type>) {
-    let num = pop_num!(stack);
+pub fn OP_MUL(stack: &mut Vec<Type>) -> Result<(), ForthError> {
+    let (y, x) = pop_two_nums(stack, "*")?;
+    push_checked(stack, "*", y, x, y.checked_mul(x))
+}
+pub fn OP_DIV(stack: &mut Vec<Type>) -> Result<(), ForthError> {
+    let (y, x) = pop_two_nums(stack, "/")?;
+    push_checked(stack, "/", y, x, y.checked_div(x))
+}
+pub fn OP_FLOOR(stack: &mut Vec<Type>) -> Result<(), ForthError> {
+    let num = pop_num(stack, "floor")?;
     match num {
         Num::Float(x) => stack.push(Type::Number(Num::Integer(x as i64))),
-        Num::Integer(x) => stack.push(Type::Number(num)),
+        Num::Integer(_) => stack.push(Type::Number(num)),
     }
+    Ok(())
 }
-pub fn OP_EQ(stack: &mut Vec<Type>) {
-    let x = pop_num!(stack);
-    let y = pop_num!(stack);
+pub fn OP_EQ(stack: &mut Vec<Type>) -> Result<(), ForthError> {
+    let (y, x) = pop_two_nums(stack, "=")?;
 
-    stack.push(Type::Boolean(y == x))
+    stack.push(Type::Boolean(y == x));
+    Ok(())
 }
-pub fn OP_GT(stack: &mut Vec<Type>) {
-    let x = pop_num!(stack);
-    let y = pop_num!(stack);
+pub fn OP_GT(stack: &mut Vec<Type>) -> Result<(), ForthError> {
+    let (y, x) = pop_two_nums(stack, ">")?;
 
-    stack.push(Type::Boolean(y > x))
+    stack.push(Type::Boolean(y > x));
+    Ok(())
 }
-pub fn OP_LT(stack: &mut Vec<Type>) {
-    let x = pop_num!(stack);
-    let y = pop_num!(stack);
+pub fn OP_LT(stack: &mut Vec<Type>) -> Result<(), ForthError> {
+    let (y, x) = pop_two_nums(stack, "<")?;
 
-    stack.push(Type::Boolean(y < x))
+    stack.push(Type::Boolean(y < x));
+    Ok(())
 }
-pub fn OP_GTEQ(stack: &mut Vec<Type>) {
-    let x = pop_num!(stack);
-    let y = pop_num!(stack);
+pub fn OP_GTEQ(stack: &mut Vec<Type>) -> Result<(), ForthError> {
+    let (y, x) = pop_two_nums(stack, ">=")?;
 
     stack.push(Type::Boolean(y <= x));
+    Ok(())
 }
-pub fn OP_LTEQ(stack: &mut Vec<Type>) {
-    let x = pop_num!(stack);
-    let y = pop_num!(stack);
+pub fn OP_LTEQ(stack: &mut Vec<Type>) -> Result<(), ForthError> {
+    let (y, x) = pop_two_nums(stack, "<=")?;
 
     stack.push(Type::Boolean(y >= x));
+    Ok(())
 }
-pub fn OP_OUT(stack: &mut Vec<Type>) {
-    let x = pop!(stack);
+pub fn OP_OUT(stack: &mut Vec<Type>, out: &mut dyn Write) -> Result<(), ForthError> {
+    let x = pop_any(stack, "out")?;
 
-    println!("{}", x);
+    writeln!(out, "{}", x).map_err(|e| ForthError::runtime(format!("`out` failed to write: {}", e)))?;
+    Ok(())
 }
-pub fn OP_DUP(stack: &mut Vec<Type>) {
-    let x = pop!(stack);
+pub fn OP_DUP(stack: &mut Vec<Type>) -> Result<(), ForthError> {
+    let x = pop_any(stack, "dup")?;
     stack.push(x.clone());
     stack.push(x);
+    Ok(())
 }
-pub fn OP_SWAP(stack: &mut Vec<Type>) {
-    let x = pop!(stack);
-    let y = pop!(stack);
+pub fn OP_SWAP(stack: &mut Vec<Type>) -> Result<(), ForthError> {
+    check_operands(stack, "swap", &[Operand::Any, Operand::Any])?;
+    let x = take(stack, Some);
+    let y = take(stack, Some);
     stack.push(x);
     stack.push(y);
+    Ok(())
 }
-pub fn OP_DROP(stack: &mut Vec<Type>) {
-    pop_num!(stack);
+pub fn OP_DROP(stack: &mut Vec<Type>) -> Result<(), ForthError> {
+    pop_any(stack, "drop")?;
+    Ok(())
 }
-pub fn OP_OVER(stack: &mut Vec<Type>) {
-    let x = pop!(stack);
-    let y = pop!(stack);
+pub fn OP_OVER(stack: &mut Vec<Type>) -> Result<(), ForthError> {
+    check_operands(stack, "over", &[Operand::Any, Operand::Any])?;
+    let x = take(stack, Some);
+    let y = take(stack, Some);
     stack.push(y.clone());
     stack.push(x);
     stack.push(y);
+    Ok(())
 }
-pub fn OP_ROTATE(stack: &mut Vec<Type>) {
-    let x = pop!(stack);
-    let y = pop!(stack);
-    let z = pop!(stack);
+pub fn OP_ROTATE(stack: &mut Vec<Type>) -> Result<(), ForthError> {
+    check_operands(stack, "rotate", &[Operand::Any, Operand::Any, Operand::Any])?;
+    let x = take(stack, Some);
+    let y = take(stack, Some);
+    let z = take(stack, Some);
     stack.push(y);
     stack.push(x);
     stack.push(z);
+    Ok(())
+}
+pub fn OP_INDEX(stack: &mut Vec<Type>) -> Result<(), ForthError> {
+    check_operands(stack, "idx", &[Operand::Int, Operand::List])?;
+    let n = take(stack, as_int);
+    let list = take(stack, as_list);
+    match list_index(n, list.len(), false) {
+        Some(i) => stack.push(list[i].clone()),
+        None => {
+            let e = out_of_range("idx", n, list.len());
+            stack.push(Type::List(list));
+            stack.push(Type::Number(Num::Integer(n)));
+            return Err(e);
+        }
+    }
+    Ok(())
+}
+pub fn OP_LEN(stack: &mut Vec<Type>) -> Result<(), ForthError> {
+    let list = pop_list(stack, "len")?;
+    stack.push(Type::Number(Num::Integer(list.len() as i64)));
+    Ok(())
+}
+pub fn OP_LIST_PUSH(stack: &mut Vec<Type>) -> Result<(), ForthError> {
+    check_operands(stack, "push", &[Operand::Any, Operand::List])?;
+    let value = take(stack, Some);
+    let mut list = take(stack, as_list);
+    list.push(value);
+    stack.push(Type::List(list));
+    Ok(())
+}
+pub fn OP_LIST_POP(stack: &mut Vec<Type>) -> Result<(), ForthError> {
+    let mut list = pop_list(stack, "pop")?;
+    match list.pop() {
+        Some(value) => {
+            stack.push(Type::List(list));
+            stack.push(value);
+        }
+        None => {
+            stack.push(Type::List(list));
+            return Err(ForthError::runtime("`pop` on an empty list"));
+        }
+    }
+    Ok(())
+}
+pub fn OP_LIST_SET(stack: &mut Vec<Type>) -> Result<(), ForthError> {
+    check_operands(stack, "set", &[Operand::Any, Operand::Int, Operand::List])?;
+    let value = take(stack, Some);
+    let i = take(stack, as_int);
+    let mut list = take(stack, as_list);
+    match list_index(i, list.len(), false) {
+        Some(n) => list[n] = value,
+        None => {
+            let e = out_of_range("set", i, list.len());
+            stack.extend([Type::List(list), Type::Number(Num::Integer(i)), value]);
+            return Err(e);
+        }
+    }
+    stack.push(Type::List(list));
+    Ok(())
+}
+pub fn OP_LIST_INSERT(stack: &mut Vec<Type>) -> Result<(), ForthError> {
+    check_operands(stack, "insert", &[Operand::Any, Operand::Int, Operand::List])?;
+    let value = take(stack, Some);
+    let i = take(stack, as_int);
+    let mut list = take(stack, as_list);
+    match list_index(i, list.len(), true) {
+        Some(n) => list.insert(n, value),
+        None => {
+            let e = out_of_range("insert", i, list.len());
+            stack.extend([Type::List(list), Type::Number(Num::Integer(i)), value]);
+            return Err(e);
+        }
+    }
+    stack.push(Type::List(list));
+    Ok(())
 }
-pub fn OP_INDEX(stack: &mut Vec<Type>) {
-    if let Num::Integer(n) = pop_num!(stack) {
-        if let Type::List(y) = pop!(stack) {
-            stack.push(y[n as usize].clone())
-        } else {
-            panic!("Expected List")
+pub fn OP_LIST_REMOVE(stack: &mut Vec<Type>) -> Result<(), ForthError> {
+    check_operands(stack, "remove", &[Operand::Int, Operand::List])?;
+    let i = take(stack, as_int);
+    let mut list = take(stack, as_list);
+    match list_index(i, list.len(), false) {
+        Some(n) => {
+            let value = list.remove(n);
+            stack.push(Type::List(list));
+            stack.push(value);
         }
-    } else {
-        panic!("Expected integer to index list")
+        None => {
+            let e = out_of_range("remove", i, list.len());
+            stack.extend([Type::List(list), Type::Number(Num::Integer(i))]);
+            return Err(e);
+        }
+    }
+    Ok(())
+}
+pub fn OP_CONCAT(stack: &mut Vec<Type>) -> Result<(), ForthError> {
+    check_operands(stack, "concat", &[Operand::List, Operand::List])?;
+    let right = take(stack, as_list);
+    let mut left = take(stack, as_list);
+    left.extend(right);
+    stack.push(Type::List(left));
+    Ok(())
+}
+// the items from `from` up to but not including `to`
+pub fn OP_SLICE(stack: &mut Vec<Type>) -> Result<(), ForthError> {
+    check_operands(stack, "slice", &[Operand::Int, Operand::Int, Operand::List])?;
+    let to = take(stack, as_int);
+    let from = take(stack, as_int);
+    let list = take(stack, as_list);
+    match (list_index(from, list.len(), true), list_index(to, list.len(), true)) {
+        (Some(start), Some(end)) if start <= end => stack.push(Type::List(list[start..end].to_vec())),
+        _ => {
+            let msg = format!("`slice` range {} to {} out of range for list of length {}", from, to, list.len());
+            stack.extend([Type::List(list), Type::Number(Num::Integer(from)), Type::Number(Num::Integer(to))]);
+            return Err(ForthError::runtime(msg));
+        }
+    }
+    Ok(())
+}
+pub fn OP_REVERSE(stack: &mut Vec<Type>) -> Result<(), ForthError> {
+    let mut list = pop_list(stack, "reverse")?;
+    list.reverse();
+    stack.push(Type::List(list));
+    Ok(())
+}
+pub fn OP_CONTAINS(stack: &mut Vec<Type>) -> Result<(), ForthError> {
+    check_operands(stack, "contains", &[Operand::Any, Operand::List])?;
+    let value = take(stack, Some);
+    let list = take(stack, as_list);
+    stack.push(Type::Boolean(list.contains(&value)));
+    Ok(())
+}
+// null when the value isn't in the list
+pub fn OP_INDEX_OF(stack: &mut Vec<Type>) -> Result<(), ForthError> {
+    check_operands(stack, "index-of", &[Operand::Any, Operand::List])?;
+    let value = take(stack, Some);
+    let list = take(stack, as_list);
+    match list.iter().position(|item| *item == value) {
+        Some(i) => stack.push(Type::Number(Num::Integer(i as i64))),
+        None => stack.push(Type::Null),
+    }
+    Ok(())
+}
+pub fn OP_FIRST(stack: &mut Vec<Type>) -> Result<(), ForthError> {
+    let list = pop_list(stack, "first")?;
+    match list.first() {
+        Some(value) => stack.push(value.clone()),
+        None => {
+            stack.push(Type::List(list));
+            return Err(ForthError::runtime("`first` on an empty list"));
+        }
+    }
+    Ok(())
+}
+pub fn OP_LAST(stack: &mut Vec<Type>) -> Result<(), ForthError> {
+    let list = pop_list(stack, "last")?;
+    match list.last() {
+        Some(value) => stack.push(value.clone()),
+        None => {
+            stack.push(Type::List(list));
+            return Err(ForthError::runtime("`last` on an empty list"));
+        }
+    }
+    Ok(())
+}
+pub fn OP_TO_LIST(stack: &mut Vec<Type>) -> Result<(), ForthError> {
+    let n = pop_int(stack, ">list")?;
+    if n < 0 || n as usize > stack.len() {
+        let msg = format!("`>list` can't take {} value(s), there are {} on the stack", n, stack.len());
+        stack.push(Type::Number(Num::Integer(n)));
+        return Err(ForthError::runtime(msg));
+    }
+    let items = stack.split_off(stack.len() - n as usize);
+    stack.push(Type::List(items));
+    Ok(())
+}
+pub fn OP_GETENV(stack: &mut Vec<Type>) -> Result<(), ForthError> {
+    let name = pop_str(stack, "getenv")?;
+    match env::var(&name) {
+        Ok(value) => stack.push(Type::Str(value)),
+        Err(_) => stack.push(Type::Null),
+    }
+    Ok(())
+}
+pub fn OP_READLN(stack: &mut Vec<Type>, input: &mut dyn BufRead) -> Result<(), ForthError> {
+    let mut line = String::new();
+    match input.read_line(&mut line) {
+        Ok(0) => stack.push(Type::Null), // nothing left to read
+        Ok(_) => {
+            let len = line.trim_end_matches(['\n', '\r']).len();
+            line.truncate(len);
+            stack.push(Type::Str(line));
+        }
+        Err(e) => return Err(ForthError::runtime(format!("`readln` failed to read: {}", e))),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn int(n: i64) -> Type {
+        Type::Number(Num::Integer(n))
+    }
+
+    fn list(items: &[i64]) -> Type {
+        Type::List(items.iter().map(|&n| int(n)).collect())
+    }
+
+    fn fails(msg: &str) -> Result<Vec<Type>, String> {
+        Err(String::from(msg))
+    }
+
+    // what `op` leaves on `stack`, the stack has to be left as it was when it fails
+    fn apply(op: fn(&mut Vec<Type>) -> Result<(), ForthError>, stack: &[Type]) -> Result<Vec<Type>, String> {
+        let mut after = stack.to_vec();
+        match op(&mut after) {
+            Ok(()) => return Ok(after),
+            Err(e) => {
+                assert_eq!(after, stack, "the stack changed on error: {}", e.message());
+                return Err(String::from(e.message()));
+            }
+        }
+    }
+
+    #[test]
+    fn operands_are_checked_before_anything_is_popped() {
+        assert_eq!(
+            apply(OP_LIST_SET, &[int(0), int(9)]),
+            fails("stack underflow, `set` needs 3 value(s) but the stack has 2")
+        );
+        assert_eq!(
+            apply(OP_LIST_SET, &[int(1), int(0), int(9)]),
+            fails("`set` expected List, got Number(1) (value 3 from the top, stack depth 3)")
+        );
+        assert_eq!(
+            apply(OP_CONCAT, &[list(&[1]), int(2)]),
+            fails("`concat` expected List, got Number(2) (value 1 from the top, stack depth 2)")
+        );
+        assert_eq!(
+            apply(OP_INDEX, &[list(&[1]), Type::Str(String::from("0"))]),
+            fails("`idx` expected Number (integer), got Str(\"0\") (value 1 from the top, stack depth 2)")
+        );
+        assert_eq!(
+            apply(OP_ADD, &[Type::Str(String::from("abc")), int(1)]),
+            fails("`+` expected Number, got Str(\"abc\") (value 2 from the top, stack depth 2)")
+        );
+        assert_eq!(apply(OP_LEN, &[]), fails("stack underflow, `len` needs 1 value(s) but the stack has 0"));
+    }
+
+    #[test]
+    fn idx() {
+        assert_eq!(apply(OP_INDEX, &[list(&[1, 2, 3]), int(0)]), Ok(vec![int(1)]));
+        assert_eq!(apply(OP_INDEX, &[list(&[1, 2, 3]), int(-1)]), Ok(vec![int(3)]));
+        assert_eq!(apply(OP_INDEX, &[list(&[1, 2, 3]), int(-3)]), Ok(vec![int(1)]));
+        assert_eq!(
+            apply(OP_INDEX, &[list(&[1, 2, 3]), int(3)]),
+            fails("`idx` index 3 out of range for list of length 3")
+        );
+        assert_eq!(
+            apply(OP_INDEX, &[list(&[1, 2, 3]), int(-4)]),
+            fails("`idx` index -4 out of range for list of length 3")
+        );
+        assert!(apply(OP_INDEX, &[list(&[]), int(0)]).is_err());
+    }
+
+    #[test]
+    fn len_push_pop() {
+        assert_eq!(apply(OP_LEN, &[list(&[1, 2])]), Ok(vec![int(2)]));
+        assert_eq!(apply(OP_LIST_PUSH, &[list(&[1]), Type::Null]), Ok(vec![Type::List(vec![int(1), Type::Null])]));
+        assert_eq!(apply(OP_LIST_POP, &[list(&[1, 2])]), Ok(vec![list(&[1]), int(2)]));
+        assert_eq!(apply(OP_LIST_POP, &[list(&[])]), fails("`pop` on an empty list"));
+    }
+
+    #[test]
+    fn set() {
+        assert_eq!(apply(OP_LIST_SET, &[list(&[1, 2]), int(1), int(9)]), Ok(vec![list(&[1, 9])]));
+        assert_eq!(apply(OP_LIST_SET, &[list(&[1, 2]), int(-2), int(9)]), Ok(vec![list(&[9, 2])]));
+        assert_eq!(
+            apply(OP_LIST_SET, &[list(&[1, 2]), int(2), int(9)]),
+            fails("`set` index 2 out of range for list of length 2")
+        );
+        assert!(apply(OP_LIST_SET, &[list(&[1, 2]), int(-3), int(9)]).is_err());
+    }
+
+    #[test]
+    fn insert_can_go_one_past_the_end() {
+        assert_eq!(apply(OP_LIST_INSERT, &[list(&[1, 2]), int(0), int(9)]), Ok(vec![list(&[9, 1, 2])]));
+        assert_eq!(apply(OP_LIST_INSERT, &[list(&[1, 2]), int(2), int(9)]), Ok(vec![list(&[1, 2, 9])]));
+        assert_eq!(apply(OP_LIST_INSERT, &[list(&[1, 2]), int(-1), int(9)]), Ok(vec![list(&[1, 9, 2])]));
+        assert_eq!(apply(OP_LIST_INSERT, &[list(&[]), int(0), int(9)]), Ok(vec![list(&[9])]));
+        assert_eq!(
+            apply(OP_LIST_INSERT, &[list(&[1, 2]), int(3), int(9)]),
+            fails("`insert` index 3 out of range for list of length 2")
+        );
+        assert!(apply(OP_LIST_INSERT, &[list(&[1, 2]), int(-3), int(9)]).is_err());
+    }
+
+    #[test]
+    fn remove() {
+        assert_eq!(apply(OP_LIST_REMOVE, &[list(&[1, 2, 3]), int(1)]), Ok(vec![list(&[1, 3]), int(2)]));
+        assert_eq!(apply(OP_LIST_REMOVE, &[list(&[1, 2, 3]), int(-1)]), Ok(vec![list(&[1, 2]), int(3)]));
+        assert_eq!(
+            apply(OP_LIST_REMOVE, &[list(&[1, 2, 3]), int(3)]),
+            fails("`remove` index 3 out of range for list of length 3")
+        );
+        assert!(apply(OP_LIST_REMOVE, &[list(&[]), int(0)]).is_err());
+    }
+
+    #[test]
+    fn concat_and_reverse() {
+        assert_eq!(apply(OP_CONCAT, &[list(&[1]), list(&[2, 3])]), Ok(vec![list(&[1, 2, 3])]));
+        assert_eq!(apply(OP_REVERSE, &[list(&[1, 2, 3])]), Ok(vec![list(&[3, 2, 1])]));
+    }
+
+    #[test]
+    fn slice() {
+        assert_eq!(apply(OP_SLICE, &[list(&[1, 2, 3, 4]), int(1), int(3)]), Ok(vec![list(&[2, 3])]));
+        assert_eq!(apply(OP_SLICE, &[list(&[1, 2, 3, 4]), int(0), int(4)]), Ok(vec![list(&[1, 2, 3, 4])]));
+        assert_eq!(apply(OP_SLICE, &[list(&[1, 2, 3, 4]), int(-2), int(4)]), Ok(vec![list(&[3, 4])]));
+        assert_eq!(apply(OP_SLICE, &[list(&[1, 2, 3, 4]), int(2), int(2)]), Ok(vec![list(&[])]));
+        assert_eq!(
+            apply(OP_SLICE, &[list(&[1, 2]), int(1), int(0)]),
+            fails("`slice` range 1 to 0 out of range for list of length 2")
+        );
+        assert!(apply(OP_SLICE, &[list(&[1, 2]), int(0), int(3)]).is_err());
+        assert!(apply(OP_SLICE, &[list(&[1, 2]), int(-3), int(1)]).is_err());
+    }
+
+    #[test]
+    fn contains_and_index_of() {
+        assert_eq!(apply(OP_CONTAINS, &[list(&[1, 2]), int(2)]), Ok(vec![Type::Boolean(true)]));
+        assert_eq!(apply(OP_CONTAINS, &[list(&[1, 2]), int(5)]), Ok(vec![Type::Boolean(false)]));
+        assert_eq!(apply(OP_INDEX_OF, &[list(&[1, 2, 2]), int(2)]), Ok(vec![int(1)]));
+        assert_eq!(apply(OP_INDEX_OF, &[list(&[1, 2]), int(5)]), Ok(vec![Type::Null]));
+    }
+
+    #[test]
+    fn first_and_last() {
+        assert_eq!(apply(OP_FIRST, &[list(&[1, 2])]), Ok(vec![int(1)]));
+        assert_eq!(apply(OP_LAST, &[list(&[1, 2])]), Ok(vec![int(2)]));
+        assert_eq!(apply(OP_FIRST, &[list(&[])]), fails("`first` on an empty list"));
+        assert_eq!(apply(OP_LAST, &[list(&[])]), fails("`last` on an empty list"));
+    }
+
+    #[test]
+    fn to_list() {
+        assert_eq!(apply(OP_TO_LIST, &[int(1), int(2), int(2)]), Ok(vec![list(&[1, 2])]));
+        assert_eq!(apply(OP_TO_LIST, &[int(1), int(0)]), Ok(vec![int(1), list(&[])]));
+        assert_eq!(
+            apply(OP_TO_LIST, &[int(1), int(2)]),
+            fails("`>list` can't take 2 value(s), there are 1 on the stack")
+        );
+        assert!(apply(OP_TO_LIST, &[int(-1)]).is_err());
     }
 }
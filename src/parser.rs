@@ -0,0 +1,462 @@
+// the parser: turns tokens into a tree with a node for every word, literal and block, which
+// `parse_to_program` then compiles into instructions. it walks the tokens with a cursor and
+// doesn't stop at the first problem, it records the error, recovers as best it can and keeps
+// going so that every structural problem in the source is reported at once
+
+use crate::error::*;
+use crate::check::Signature;
+use crate::lexer::*;
+use crate::builtins;
+
+#[derive(Debug)]
+pub(crate) enum Node<'a> {
+    // a literal, a built in word, a call or a variable access, which one is sorted out when it's compiled
+    Token(Token<'a>),
+    // `[1 "two" [3]]`, everything in it is a literal: a `Token` holding a number, string, boolean or null, or another list
+    List { open: Token<'a>, items: Vec<Node<'a>>, close: Option<Token<'a>> },
+    // `{ !a !b 1 + }`, a list of whatever `body` leaves on the stack when it runs
+    Build { open: Token<'a>, body: Vec<Node<'a>>, close: Option<Token<'a>> },
+    Defvar { keyword: Token<'a>, name: Token<'a> },
+    // `if ... else <condition> if* ... else ... end`, `end` is missing when the chain was never closed
+    If { keyword: Token<'a>, body: Vec<Node<'a>>, elses: Vec<Else<'a>>, end: Option<Token<'a>> },
+    // `while <condition> do ... end`, `body` is the `do` and what follows it
+    While {
+        keyword: Token<'a>,
+        condition: Vec<Node<'a>>,
+        body: Option<(Token<'a>, Vec<Node<'a>>)>,
+        end: Option<Token<'a>>,
+    },
+    // `defword name ( a -- b ) ... return`
+    Defword {
+        keyword: Token<'a>,
+        name: Token<'a>,
+        sig: Option<Signature>,
+        body: Vec<Node<'a>>,
+        end: Option<Token<'a>>,
+    },
+}
+
+// an `else` in an if-else chain. when `branch` is there `body` is the condition of that `if*`,
+// otherwise it's the last branch of the chain
+#[derive(Debug)]
+pub(crate) struct Else<'a> {
+    pub keyword: Token<'a>,
+    pub body: Vec<Node<'a>>,
+    pub branch: Option<(Token<'a>, Vec<Node<'a>>)>,
+}
+
+// how a block came to an end
+enum Ending<'a> {
+    Keyword(Token<'a>), // one of the words it was waiting for, already taken
+    Return(Token<'a>), // the `return` of the word it's in, left for the `defword` to take
+    Eof,
+}
+
+// how many blocks and lists can sit inside each other, the parser and everything after it recurses
+// once per level so without a limit deep enough nesting runs out of stack instead of reporting an error
+const MAX_NESTING: usize = 100;
+
+struct Parser<'t, 'a> {
+    tokens: &'t [Token<'a>],
+    pos: usize,
+    words: Vec<Token<'a>>, // the `defword`s we're inside of, innermost last
+    depth: usize, // how many blocks and lists we're inside of
+    errors: Vec<ForthError>,
+}
+
+// the whole of `tokens`, along with everything that is wrong with it
+pub(crate) fn parse<'a>(tokens: &[Token<'a>]) -> (Vec<Node<'a>>, Vec<ForthError>) {
+    let mut parser = Parser { tokens, pos: 0, words: vec![], depth: 0, errors: vec![] };
+    let (nodes, _) = parser.block(&[]);
+    return (nodes, parser.errors);
+}
+
+impl<'t, 'a> Parser<'t, 'a> {
+    fn peek(&self) -> Option<Token<'a>> {
+        self.tokens.get(self.pos).copied()
+    }
+
+    fn next(&mut self) -> Option<Token<'a>> {
+        let token = self.peek()?;
+        self.pos += 1;
+        return Some(token);
+    }
+
+    // nodes up to one of the keywords in `until`, the end of the input or the `return` of the current word
+    fn block(&mut self, until: &[&str]) -> (Vec<Node<'a>>, Ending<'a>) {
+        let mut nodes = vec![];
+        while let Some(token) = self.peek() {
+            match token.kind {
+                TokenKind::Word => (),
+                TokenKind::ListStart => {
+                    self.pos += 1;
+                    nodes.extend(self.nested(token, Self::list));
+                    continue;
+                }
+                TokenKind::ListEnd => {
+                    self.pos += 1;
+                    self.errors.push(ForthError::parse(token.span, "`]` without a matching `[`").with_label("no list to close"));
+                    continue;
+                }
+                _ => {
+                    self.pos += 1;
+                    nodes.push(Node::Token(token));
+                    continue;
+                }
+            }
+            if until.contains(&token.text) {
+                self.pos += 1;
+                return (nodes, Ending::Keyword(token));
+            }
+            if token.text == "return" && !self.words.is_empty() {
+                return (nodes, Ending::Return(token));
+            }
+            self.pos += 1;
+            let span = token.span;
+            match token.text {
+                "if" => nodes.extend(self.nested(token, Self::if_chain)),
+                "if*" => {
+                    // `if*` only makes sense as the condition of an `else`
+                    let e = ForthError::parse(span, "`if*` without a preceding `else`")
+                        .with_label("not part of an if-else chain")
+                        .with_note(None, "else-if branches are written as `else <condition> if*`");
+                    self.errors.push(e);
+                    nodes.extend(self.nested(token, Self::if_chain)); // treat it as a plain `if` so the matching `end` still lines up
+                }
+                "while" => nodes.extend(self.nested(token, Self::while_loop)),
+                "{" => nodes.extend(self.nested(token, Self::build)),
+                "}" => self.errors.push(ForthError::parse(span, "`}` without a matching `{`").with_label("no list to close")),
+                "defword" => nodes.extend(self.nested(token, Self::defword).flatten()),
+                "defvar" => match self.next() {
+                    Some(name) => nodes.push(Node::Defvar { keyword: token, name }),
+                    None => {
+                        let e = ForthError::parse(span, "expected variable name after `defvar`").with_label("missing variable name");
+                        self.errors.push(e);
+                    }
+                },
+                "else" => self.errors.push(ForthError::parse(span, "dangling `else`").with_label("no `if` to attach to")),
+                "do" => {
+                    let e = ForthError::parse(span, "`do` without a matching `while`").with_label("no `while` before this");
+                    self.errors.push(e);
+                }
+                // every block but a word waits for `end`, so this is either in a word or outside of any block
+                "end" => match self.words.last() {
+                    Some(word) => {
+                        let e = ForthError::parse(span, "use `return` to end word declarations")
+                            .with_label("expected `return`")
+                            .with_note(Some(word.span), "word declaration starts here");
+                        self.errors.push(e);
+                    }
+                    None => self.errors.push(ForthError::parse(span, "dangling `end`").with_label("no open block to close")),
+                },
+                "return" => {
+                    let e = ForthError::parse(span, "`return` expected to be used in word declaration")
+                        .with_label("not inside a `defword`");
+                    self.errors.push(e);
+                }
+                _ => nodes.push(Node::Token(token)),
+            }
+        }
+        return (nodes, Ending::Eof);
+    }
+
+    // the block or list `open` starts, parsed by `parse`. past `MAX_NESTING` levels it's reported
+    // and skipped over instead, by counting what opens and closes without looking any deeper
+    fn nested<T>(&mut self, open: Token<'a>, parse: fn(&mut Self, Token<'a>) -> T) -> Option<T> {
+        if self.depth < MAX_NESTING {
+            self.depth += 1;
+            let node = parse(self, open);
+            self.depth -= 1;
+            return Some(node);
+        }
+
+        let e = ForthError::parse(open.span, "blocks and lists are nested too deeply")
+            .with_label(format!("more than {} levels deep", MAX_NESTING))
+            .with_note(None, "everything up to where this is closed is skipped, move some of it into words");
+        self.errors.push(e);
+        let mut level = 1;
+        while level > 0 {
+            let token = match self.next() {
+                Some(token) => token,
+                None => break,
+            };
+            match (token.kind, token.text) {
+                (TokenKind::ListStart, _) | (TokenKind::Word, "if" | "while" | "{" | "defword") => level += 1,
+                (TokenKind::ListEnd, _) | (TokenKind::Word, "end" | "}" | "return") => level -= 1,
+                _ => (),
+            }
+        }
+        return None;
+    }
+
+    // `open` is the keyword of the part of a block that was still going when `ending` came along,
+    // `start` is where the block began if that isn't `open` itself
+    fn unclosed(&mut self, open: Token<'a>, ending: &Ending<'a>, start: Option<Span>) {
+        let e = match ending {
+            Ending::Return(ret) => {
+                ForthError::parse(open.span, format!("unclosed `{}` before `return`", open.text))
+                    .with_label("never closed")
+                    .with_note(Some(ret.span), "the word declaration ends here")
+            }
+            _ => {
+                let msg = match open.text {
+                    "if" => "Unclosed `if`",
+                    "if*" => "Unclosed `if*`",
+                    "else" => "unclosed `else`",
+                    "do" => "`do` block after loop condition is unclosed",
+                    "while" => "`while` loop unclosed",
+                    "{" => "Unclosed `{`",
+                    _ => "word declaration unclosed, expected `return`",
+                };
+                let e = ForthError::parse(open.span, msg).with_label("never closed");
+                match start {
+                    Some(start) => e.with_note(Some(start), "loop starts here"),
+                    None => e,
+                }
+            }
+        };
+        self.errors.push(e);
+    }
+
+    // the rest of a list literal, `open` is its `[`
+    fn list(&mut self, open: Token<'a>) -> Node<'a> {
+        let mut items = vec![];
+        while let Some(token) = self.next() {
+            match token.kind {
+                TokenKind::ListEnd => return Node::List { open, items, close: Some(token) },
+                TokenKind::ListStart => items.extend(self.nested(token, Self::list)),
+                TokenKind::Num | TokenKind::Str | TokenKind::Bool | TokenKind::Null => items.push(Node::Token(token)),
+                _ => {
+                    let msg = format!("expected valid type in list, got {}", token.text);
+                    let e = ForthError::parse(token.span, msg).with_label("lists can only hold numbers, strings, booleans and null");
+                    self.errors.push(e);
+                }
+            }
+        }
+        self.errors.push(ForthError::parse(open.span, "Unclosed List").with_label("list starts here"));
+        return Node::List { open, items, close: None };
+    }
+
+    // the rest of a list built at runtime, `open` is its `{`
+    fn build(&mut self, open: Token<'a>) -> Node<'a> {
+        let (body, ending) = self.block(&["}"]);
+        match ending {
+            Ending::Keyword(close) => return Node::Build { open, body, close: Some(close) },
+            _ => {
+                self.unclosed(open, &ending, None);
+                return Node::Build { open, body, close: None };
+            }
+        }
+    }
+
+    // everything after `keyword`, which is an `if` or an `if*` that isn't part of a chain
+    fn if_chain(&mut self, keyword: Token<'a>) -> Node<'a> {
+        let (body, mut ending) = self.block(&["else", "end"]);
+        let mut elses = vec![];
+        let mut open = keyword;
+        loop {
+            match ending {
+                Ending::Keyword(end) if end.text == "end" => {
+                    return Node::If { keyword, body, elses, end: Some(end) };
+                }
+                Ending::Keyword(else_keyword) => {
+                    let (else_body, else_ending) = self.block(&["if*", "end"]);
+                    match else_ending {
+                        Ending::Keyword(ifstar) if ifstar.text == "if*" => {
+                            let (branch, branch_ending) = self.block(&["else", "end"]);
+                            elses.push(Else { keyword: else_keyword, body: else_body, branch: Some((ifstar, branch)) });
+                            open = ifstar;
+                            ending = branch_ending;
+                        }
+                        // an `else` without `if*` is the last one, nothing but `end` can follow
+                        else_ending => {
+                            elses.push(Else { keyword: else_keyword, body: else_body, branch: None });
+                            open = else_keyword;
+                            ending = else_ending;
+                        }
+                    }
+                }
+                _ => {
+                    self.unclosed(open, &ending, None);
+                    return Node::If { keyword, body, elses, end: None };
+                }
+            }
+        }
+    }
+
+    fn while_loop(&mut self, keyword: Token<'a>) -> Node<'a> {
+        let (condition, ending) = self.block(&["do", "end"]);
+        match ending {
+            Ending::Keyword(do_keyword) if do_keyword.text == "do" => {
+                let (body, ending) = self.block(&["end"]);
+                let end = match ending {
+                    Ending::Keyword(end) => Some(end),
+                    _ => {
+                        let start = match ending {
+                            Ending::Eof => Some(keyword.span),
+                            _ => None,
+                        };
+                        self.unclosed(do_keyword, &ending, start);
+                        None
+                    }
+                };
+                return Node::While { keyword, condition, body: Some((do_keyword, body)), end };
+            }
+            Ending::Keyword(end) => {
+                let e = ForthError::parse(end.span, "`end` expected `do` after `while`")
+                    .with_label("loop has no `do`")
+                    .with_note(Some(keyword.span), "loop starts here");
+                self.errors.push(e);
+                return Node::While { keyword, condition, body: None, end: Some(end) };
+            }
+            _ => {
+                self.unclosed(keyword, &ending, None);
+                return Node::While { keyword, condition, body: None, end: None };
+            }
+        }
+    }
+
+    fn defword(&mut self, keyword: Token<'a>) -> Option<Node<'a>> {
+        let name = match self.next() {
+            Some(name) => name,
+            None => {
+                let e = ForthError::parse(keyword.span, "expected word name after `defword`").with_label("missing word name");
+                self.errors.push(e);
+                return None;
+            }
+        };
+        // the word still gets parsed so the rest of the source lines up, it could just never be called
+        let reason = match name.kind {
+            TokenKind::Word if builtins().any(|b| b == name.text) => Some("this is a built in word"),
+            TokenKind::Word => None,
+            TokenKind::VarOp => Some("this reads or writes a variable"),
+            _ => Some("this is a literal"),
+        };
+        if let Some(reason) = reason {
+            let e = ForthError::parse(name.span, format!("`{}` can't be used as a word name", name.text))
+                .with_label(reason)
+                .with_note(None, "calls would never reach a word declared with this name, pick another one");
+            self.errors.push(e);
+        }
+        let mut sig = None;
+        if self.peek().map(|t| t.text) == Some("(") {
+            match self.signature() {
+                Ok(s) => sig = Some(s),
+                Err(e) => self.errors.push(e),
+            }
+        }
+
+        self.words.push(keyword);
+        let (body, ending) = self.block(&[]);
+        self.words.pop();
+        let end = match ending {
+            Ending::Return(ret) => {
+                self.pos += 1;
+                Some(ret)
+            }
+            _ => {
+                self.unclosed(keyword, &ending, None);
+                None
+            }
+        };
+        return Some(Node::Defword { keyword, name, sig, body, end });
+    }
+
+    // a `( a b -- c )` stack effect, the `(` has to be the next token
+    fn signature(&mut self) -> Result<Signature, ForthError> {
+        let open = self.next().unwrap().span;
+        let mut inputs: Vec<String> = vec![];
+        let mut outputs: Vec<String> = vec![];
+        let mut seen_dashes = false;
+        loop {
+            let token = match self.next() {
+                Some(token) => token,
+                None => return Err(ForthError::parse(open, "unclosed stack effect").with_label("expected `)` to close this")),
+            };
+            match token.text {
+                ")" => break,
+                "--" if seen_dashes => {
+                    return Err(ForthError::parse(token.span, "stack effect has more than one `--`").with_label("second `--`"));
+                }
+                "--" => seen_dashes = true,
+                name if seen_dashes => outputs.push(String::from(name)),
+                name => inputs.push(String::from(name)),
+            }
+        }
+        if !seen_dashes {
+            let e = ForthError::parse(open, "stack effect is missing `--`")
+                .with_label("expected `( inputs -- outputs )`");
+            return Err(e);
+        }
+        return Ok(Signature { inputs, outputs });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // the messages of everything wrong with `source`, in the order they were found
+    fn errors(source: &str) -> Vec<String> {
+        let tokens = tokenize(source, 0).unwrap();
+        let (_, errors) = parse(&tokens);
+        return errors.iter().map(|e| String::from(e.message())).collect();
+    }
+
+    #[test]
+    fn well_formed_source_has_no_errors() {
+        assert!(errors("defword sq ( n -- n ) dup * return 1 if 2 else 3 if* 4 else 5 end").is_empty());
+        assert!(errors("while 1 do end [1 [\"a\" null] true] { 1 }").is_empty());
+    }
+
+    #[test]
+    fn unclosed_blocks() {
+        assert_eq!(errors("1 if 2"), ["Unclosed `if`"]);
+        assert_eq!(errors("1 if 2 else 3 if* 4"), ["Unclosed `if*`"]);
+        assert_eq!(errors("while 1"), ["`while` loop unclosed"]);
+        assert_eq!(errors("while 1 do 2"), ["`do` block after loop condition is unclosed"]);
+        assert_eq!(errors("defword w 1"), ["word declaration unclosed, expected `return`"]);
+        assert_eq!(errors("{ 1"), ["Unclosed `{`"]);
+        assert_eq!(errors("[1 [2]"), ["Unclosed List"]);
+        assert_eq!(errors("defword w 1 if return"), ["unclosed `if` before `return`"]);
+    }
+
+    #[test]
+    fn stray_closers() {
+        assert_eq!(errors("end"), ["dangling `end`"]);
+        assert_eq!(errors("else"), ["dangling `else`"]);
+        assert_eq!(errors("1 ]"), ["`]` without a matching `[`"]);
+        assert_eq!(errors("}"), ["`}` without a matching `{`"]);
+        assert_eq!(errors("return"), ["`return` expected to be used in word declaration"]);
+        assert_eq!(errors("defword w end return"), ["use `return` to end word declarations"]);
+    }
+
+    #[test]
+    fn word_names_cant_be_taken() {
+        assert_eq!(errors("defword first ( n -- n ) 10 * return"), ["`first` can't be used as a word name"]);
+        assert_eq!(errors("defword if return"), ["`if` can't be used as a word name"]);
+        assert_eq!(errors("defword null return"), ["`null` can't be used as a word name"]);
+        assert_eq!(errors("defword 3 return"), ["`3` can't be used as a word name"]);
+        assert_eq!(errors("defword \"s\" return"), ["`s` can't be used as a word name"]);
+        assert_eq!(errors("defword !x return"), ["`!x` can't be used as a word name"]);
+        assert!(errors("defword firsts return defword sq return").is_empty());
+    }
+
+    #[test]
+    fn lists_only_hold_literals() {
+        assert_eq!(errors("[1 dup]"), ["expected valid type in list, got dup"]);
+        assert_eq!(errors("[1 [2 !x]]"), ["expected valid type in list, got !x"]);
+    }
+
+    #[test]
+    fn deep_nesting_is_an_error() {
+        let depth = 20_000;
+        let source = format!("{} 2 {} 3", "1 if ".repeat(depth), "end ".repeat(depth));
+        assert_eq!(errors(&source), ["blocks and lists are nested too deeply"]);
+        let source = format!("{}{} 3", "[".repeat(depth), "]".repeat(depth));
+        assert_eq!(errors(&source), ["blocks and lists are nested too deeply"]);
+
+        let source = format!("{} 2 {}", "1 if ".repeat(MAX_NESTING), "end ".repeat(MAX_NESTING));
+        assert!(errors(&source).is_empty());
+    }
+}
@@ -0,0 +1,134 @@
+use std::io;
+use myforth::*;
+use crate::*;
+
+const COMMANDS: &[&str] = &[":stack", ":words", ":vars", ":see", ":load", ":reset", ":clear", ":debug", ":quit"];
+
+// what the loop should do after a `:command`
+enum Command {
+    Continue,
+    Quit,
+}
+
+// `:commands` look at and manage the session instead of being evaluated
+fn command(interp: &mut Interpreter, line: &str, options: &Options) -> Command {
+    let mut parts = line.split_whitespace();
+    let name = parts.next().unwrap_or(":");
+    let arg = parts.next();
+    let mut out = String::new(); // goes to the interpreter's output at the end
+    match (name, arg) {
+        (":stack", None) => out = format!("STACK TRACE: {}\n", format_stack(interp.stack())),
+        (":words", None) => {
+            for (name, sig) in interp.words() {
+                match sig {
+                    Some(sig) => out.push_str(&format!("{} {}\n", name, sig)),
+                    None => out.push_str(&format!("{}\n", name)),
+                }
+            }
+        }
+        (":vars", None) => {
+            for (name, value) in interp.vars() {
+                out.push_str(&format!("{} = {}\n", name, value));
+            }
+        }
+        (":see", Some(word)) => match interp.see(word) {
+            Some(definition) => out = format!("{}\n", definition),
+            None => out = format!("`{}` is not a defined word\n", word),
+        },
+        (":load", Some(path)) => {
+            if let Err(e) = interp.load_file(path) {
+                report(interp, &e);
+            }
+        }
+        (":reset", None) => {
+            interp.reset();
+            preload(interp, &options.preludes); // startup files are part of a fresh session
+        }
+        (":clear", None) => interp.clear_stack(),
+        (":debug", Some(switch @ ("on" | "off"))) => {
+            interp.dump_tokens = switch == "on";
+            interp.dump_ops = switch == "on";
+        }
+        (":quit", None) => return Command::Quit,
+        _ => out = format!("unknown command `{}`, try one of {}\n", line.trim(), COMMANDS.join(" ")),
+    }
+    let output = interp.output();
+    let _ = output.write_all(out.as_bytes()).and_then(|_| output.flush());
+    return Command::Continue;
+}
+
+// everything tab completion can offer: built in words, commands, declared words and variables
+fn completions(interp: &Interpreter) -> Vec<String> {
+    let mut words: Vec<String> = builtins().chain(COMMANDS.iter().copied()).map(String::from).collect();
+    words.extend(interp.words().into_iter().map(|(name, _)| String::from(name)));
+    for (name, _) in interp.vars() {
+        words.push(String::from(name));
+        words.push(format!("@{}", name));
+        words.push(format!("!{}", name));
+    }
+    words.sort();
+    words.dedup();
+    return words;
+}
+
+// `interp` already has the startup files loaded, they are loaded again after `:reset`.
+// returns the exit status, which the program can set with `exit`
+pub fn repl(mut interp: Interpreter, options: &Options) -> i32 {
+    let mut input = String::new();
+    let mut editor = Editor::new();
+
+    println!("\nWelcome to Bombo's Forth Interactive Environment Repl");
+    loop {
+        // `..` means the last line left something open
+        let prompt = if input.is_empty() {
+            println!();
+            ">> "
+        } else {
+            ".. "
+        };
+        let line = match editor.read_line(prompt, &completions(&interp)) {
+            Ok(line) => line,
+            // ctrl-c throws away whatever was typed so far
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {
+                input.clear();
+                continue;
+            }
+            Err(e) => {
+                eprintln!("error: {}", e);
+                break;
+            }
+        };
+        match line {
+            None if input.is_empty() => break, // EOF, ctrl-d
+            None => (), // the input ended inside a block, run what there is
+            Some(line) => {
+                input.push_str(&line);
+                input.push('\n');
+                if input.trim_start().starts_with(':') {
+                    let line = input.clone();
+                    input.clear();
+                    match command(&mut interp, &line, options) {
+                        Command::Continue => continue,
+                        Command::Quit => break,
+                    }
+                }
+                if is_incomplete(&input) {
+                    continue;
+                }
+            }
+        }
+
+        // a mistake only throws away the block it was made in
+        if let Err(e) = interp.eval_named("<repl>", &input) {
+            report(&interp, &e);
+        }
+        if let Some(code) = interp.exit_code() {
+            return code;
+        }
+        if options.stack_trace {
+            let _ = interp.show_stack(interp.dump_ops);
+        }
+        input.clear();
+    }
+    return 0;
+}
@@ -1,5 +1,4 @@
 use std::fmt;
-use std::ops::{Add, Sub, Mul, Div};
 use std::cmp::Ordering;
 
 #[derive(Copy, Clone, Debug)]
@@ -17,6 +16,29 @@ pub enum Type {
     List(Vec<Type>),
 }
 
+impl Type {
+    // the variant name, for error messages
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Type::Null => "Null",
+            Type::Boolean(_) => "Boolean",
+            Type::Number(_) => "Number",
+            Type::Str(_) => "Str",
+            Type::List(_) => "List",
+        }
+    }
+
+    // variant and value, `Str("abc")`, `Number(3)`
+    pub fn describe(&self) -> String {
+        match self {
+            Type::Null => String::from("Null"),
+            Type::Str(s) => format!("Str({:?})", s),
+            v => format!("{}({})", v.type_name(), v),
+        }
+    }
+}
+
+#[allow(clippy::redundant_guards, clippy::comparison_to_empty, clippy::bool_comparison)]
 pub fn is_falsy(t: Type) -> bool {
     match t {
         Type::Number(n) => {
@@ -33,16 +55,6 @@ pub fn is_falsy(t: Type) -> bool {
     }
 }
 
-pub fn show_list(stack: &Vec<Type>) {
-    let mut output = String::from("[ ");
-    for v in stack {
-        let val = format!("{} ", v);
-        output.push_str(&val);
-    }
-    output.push(']');
-    println!("STACK TRACE: {}", output);
-}
-
 impl fmt::Display for Type {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
@@ -71,52 +83,61 @@ impl fmt::Display for Num {
     }
 }
 
-impl Add for Num {
-    type Output = Num;
-    fn add(self, other: Num) -> Num {
+// arithmetic on integers stays in integers and fails on overflow, the `checked_*` methods
+// give back `None` for that instead of panicking. anything involving a float is done in floats
+impl Num {
+    pub fn checked_add(self, other: Num) -> Option<Num> {
         match (self, other) {
-            (Num::Integer(n1), Num::Integer(n2)) => Num::Integer(n1 + n2),
-            (Num::Integer(n), Num::Float(f)) => Num::Float(n as f64 + f), 
+            (Num::Integer(n1), Num::Integer(n2)) => n1.checked_add(n2).map(Num::Integer),
+            (Num::Integer(n), Num::Float(f)) => Some(Num::Float(n as f64 + f)),
 
-            (Num::Float(f1), Num::Float(f2)) => Num::Float(f1 + f2),
-            (Num::Float(f), Num::Integer(n)) => Num::Float(f + n as f64),
+            (Num::Float(f1), Num::Float(f2)) => Some(Num::Float(f1 + f2)),
+            (Num::Float(f), Num::Integer(n)) => Some(Num::Float(f + n as f64)),
         }
     }
-}
-impl Sub for Num {
-    type Output = Num;
-    fn sub(self, other: Num) -> Num {
+
+    pub fn checked_sub(self, other: Num) -> Option<Num> {
         match (self, other) {
-            (Num::Integer(n1), Num::Integer(n2)) => Num::Integer(n1 - n2),
-            (Num::Integer(n), Num::Float(f)) => Num::Float(n as f64 - f), 
+            (Num::Integer(n1), Num::Integer(n2)) => n1.checked_sub(n2).map(Num::Integer),
+            (Num::Integer(n), Num::Float(f)) => Some(Num::Float(n as f64 - f)),
 
-            (Num::Float(f1), Num::Float(f2)) => Num::Float(f1 - f2),
-            (Num::Float(f), Num::Integer(n)) => Num::Float(f - n as f64),
+            (Num::Float(f1), Num::Float(f2)) => Some(Num::Float(f1 - f2)),
+            (Num::Float(f), Num::Integer(n)) => Some(Num::Float(f - n as f64)),
         }
     }
-}
-impl Mul for Num {
-    type Output = Num;
-    fn mul(self, other: Num) -> Num {
+
+    pub fn checked_mul(self, other: Num) -> Option<Num> {
         match (self, other) {
-            (Num::Integer(n1), Num::Integer(n2)) => Num::Integer(n1 * n2),
-            (Num::Integer(n), Num::Float(f)) => Num::Float(n as f64 * f), 
+            (Num::Integer(n1), Num::Integer(n2)) => n1.checked_mul(n2).map(Num::Integer),
+            (Num::Integer(n), Num::Float(f)) => Some(Num::Float(n as f64 * f)),
 
-            (Num::Float(f1), Num::Float(f2)) => Num::Float(f1 * f2),
-            (Num::Float(f), Num::Integer(n)) => Num::Float(f * n as f64),
+            (Num::Float(f1), Num::Float(f2)) => Some(Num::Float(f1 * f2)),
+            (Num::Float(f), Num::Integer(n)) => Some(Num::Float(f * n as f64)),
         }
     }
-}
 
-impl Div for Num {
-    type Output = Num;
-    fn div(self, other: Num) -> Num {
+    // `None` for an integer divided by integer zero as well
+    pub fn checked_div(self, other: Num) -> Option<Num> {
         match (self, other) {
-            (Num::Integer(n1), Num::Integer(n2)) => Num::Float((n1 / n2) as f64),
-            (Num::Integer(n), Num::Float(f)) => Num::Float(n as f64 / f), 
+            (Num::Integer(n1), Num::Integer(n2)) => n1.checked_div(n2).map(|n| Num::Float(n as f64)),
+            (Num::Integer(n), Num::Float(f)) => Some(Num::Float(n as f64 / f)),
 
-            (Num::Float(f1), Num::Float(f2)) => Num::Float(f1 / f2),
-            (Num::Float(f), Num::Integer(n)) => Num::Float(f / n as f64),
+            (Num::Float(f1), Num::Float(f2)) => Some(Num::Float(f1 / f2)),
+            (Num::Float(f), Num::Integer(n)) => Some(Num::Float(f / n as f64)),
+        }
+    }
+}
+
+// the same type holding the same value, numbers compare across integers and floats like `=` does
+impl PartialEq for Type {
+    fn eq(&self, other: &Type) -> bool {
+        match (self, other) {
+            (Type::Null, Type::Null) => true,
+            (Type::Boolean(left), Type::Boolean(right)) => left == right,
+            (Type::Number(left), Type::Number(right)) => left == right,
+            (Type::Str(left), Type::Str(right)) => left == right,
+            (Type::List(left), Type::List(right)) => left == right,
+            _ => false,
         }
     }
 }